@@ -0,0 +1,106 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares the fair and the unfair [`Mutex`] under contention.
+//!
+//! Run with `cargo bench -p mea --bench mutex`. For each number of contending tasks, every task
+//! locks the mutex repeatedly for a short critical section that yields once, and the time each
+//! `lock` call waits is recorded to report the throughput and the latency percentiles.
+//!
+//! Every run uses a multi-threaded Tokio runtime with [`WORKERS`] worker threads, whatever the
+//! number of cores of the machine.
+
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use mea::mutex::Mutex;
+
+const WORKERS: usize = 4;
+const TASKS: [usize; 4] = [2, 4, 8, 16];
+const LOCKS_PER_TASK: usize = 20_000;
+
+struct Report {
+    elapsed: Duration,
+    latencies: Vec<Duration>,
+}
+
+impl Report {
+    fn percentile(&self, p: f64) -> Duration {
+        let idx = ((self.latencies.len() - 1) as f64 * p).round() as usize;
+        self.latencies[idx]
+    }
+}
+
+fn contend(mutex: Mutex<u64>, tasks: usize) -> Report {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(WORKERS)
+        .build()
+        .unwrap();
+
+    rt.block_on(async move {
+        let mutex = Arc::new(mutex);
+        let start = Instant::now();
+        let handles = (0..tasks)
+            .map(|_| {
+                let mutex = mutex.clone();
+                tokio::spawn(async move {
+                    let mut latencies = Vec::with_capacity(LOCKS_PER_TASK);
+                    for _ in 0..LOCKS_PER_TASK {
+                        let begin = Instant::now();
+                        let mut guard = mutex.lock().await;
+                        latencies.push(begin.elapsed());
+                        *guard += 1;
+                        // hold the lock across a suspension point, so that others queue up
+                        tokio::task::yield_now().await;
+                        drop(guard);
+                    }
+                    latencies
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut latencies = Vec::with_capacity(tasks * LOCKS_PER_TASK);
+        for handle in handles {
+            latencies.extend(handle.await.unwrap());
+        }
+        let elapsed = start.elapsed();
+        assert_eq!(*mutex.lock().await, (tasks * LOCKS_PER_TASK) as u64);
+
+        latencies.sort_unstable();
+        Report { elapsed, latencies }
+    })
+}
+
+fn main() {
+    println!(
+        "{:<8} {:>6} {:>14} {:>12} {:>12} {:>12}",
+        "mutex", "tasks", "locks/s", "p50", "p99", "max"
+    );
+    for tasks in TASKS {
+        for (name, mutex) in [("fair", Mutex::new(0)), ("unfair", Mutex::new_unfair(0))] {
+            let report = contend(mutex, tasks);
+            let throughput = report.latencies.len() as f64 / report.elapsed.as_secs_f64();
+            println!(
+                "{:<8} {:>6} {:>14.0} {:>12?} {:>12?} {:>12?}",
+                name,
+                tasks,
+                throughput,
+                report.percentile(0.5),
+                report.percentile(0.99),
+                report.latencies[report.latencies.len() - 1],
+            );
+        }
+    }
+}
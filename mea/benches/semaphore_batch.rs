@@ -0,0 +1,105 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares acquiring permits one at a time with [`Semaphore::reserve_batch`] in a hot loop.
+//!
+//! Run with `cargo bench -p mea --bench semaphore_batch`. For each number of contending tasks,
+//! every task handles its items one permit at a time, either acquiring each permit from the
+//! semaphore, or taking it from a batch reserved ahead, and the throughput is reported.
+//!
+//! Every run uses a multi-threaded Tokio runtime with [`WORKERS`] worker threads, whatever the
+//! number of cores of the machine.
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use mea::semaphore::Semaphore;
+
+const WORKERS: usize = 4;
+const PERMITS: u32 = 64;
+const TASKS: [usize; 3] = [1, 4, 16];
+const ITEMS_PER_TASK: u32 = 200_000;
+
+/// How each task acquires the permit for an item: `None` for one at a time, or the size of the
+/// batches to reserve.
+const MODES: [(&str, Option<u32>); 3] = [
+    ("per-item", None),
+    ("batch-8", Some(8)),
+    ("batch-32", Some(32)),
+];
+
+fn contend(batch: Option<u32>, tasks: usize) -> Duration {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(WORKERS)
+        .build()
+        .unwrap();
+
+    rt.block_on(async move {
+        let sem = Arc::new(Semaphore::new(PERMITS));
+        let start = Instant::now();
+        let handles = (0..tasks)
+            .map(|_| {
+                let sem = sem.clone();
+                tokio::spawn(async move {
+                    match batch {
+                        None => {
+                            for item in 0..ITEMS_PER_TASK {
+                                let permit = sem.acquire(1).await;
+                                black_box((item, &permit));
+                            }
+                        }
+                        Some(size) => {
+                            for items in (0..ITEMS_PER_TASK).step_by(size as usize) {
+                                let mut batch = sem.reserve_batch(size).await;
+                                for item in items..items + size {
+                                    let permit = batch.take().unwrap();
+                                    black_box((item, &permit));
+                                }
+                            }
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        let elapsed = start.elapsed();
+        assert_eq!(sem.available_permits(), PERMITS);
+        elapsed
+    })
+}
+
+fn main() {
+    println!(
+        "{:<10} {:>6} {:>14} {:>12}",
+        "acquire", "tasks", "items/s", "elapsed"
+    );
+    for tasks in TASKS {
+        for (name, batch) in MODES {
+            let elapsed = contend(batch, tasks);
+            let items = tasks as f64 * ITEMS_PER_TASK as f64;
+            println!(
+                "{:<10} {:>6} {:>14.0} {:>12?}",
+                name,
+                tasks,
+                items / elapsed.as_secs_f64(),
+                elapsed,
+            );
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares the single-shot `try_*` methods with their `try_*_spin` variants under contention.
+//!
+//! Run with `cargo bench -p mea --bench spin`. For each primitive, [`THREADS`] threads attempt to
+//! lock it repeatedly, hold it for a short critical section on success, and pause briefly between
+//! attempts. The share of attempts that succeeded and the rate of attempts are reported.
+
+use std::hint::spin_loop;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use mea::mutex::Mutex;
+use mea::rwlock::RwLock;
+use mea::semaphore::Semaphore;
+
+const THREADS: usize = 4;
+const ATTEMPTS_PER_THREAD: usize = 200_000;
+/// The number of spin loop hints a successful attempt holds the lock for.
+const HOLD: usize = 32;
+/// The number of spin loop hints between two attempts.
+const PAUSE: usize = 64;
+
+/// How each attempt is made: `None` for the single-shot `try_*`, or the spins to allow.
+const MODES: [(&str, Option<u32>); 3] = [
+    ("try", None),
+    ("spin-16", Some(16)),
+    ("spin-256", Some(256)),
+];
+
+fn busy(hints: usize) {
+    for _ in 0..hints {
+        spin_loop();
+    }
+}
+
+/// Runs `attempt` from every thread, and returns the number of successful attempts and the
+/// elapsed time. The thread index is passed along, so that attempts can differ per thread.
+fn contend(attempt: impl Fn(usize) -> bool + Sync) -> (usize, Duration) {
+    let start = Instant::now();
+    let succeeded = thread::scope(|s| {
+        let handles = (0..THREADS)
+            .map(|idx| {
+                let attempt = &attempt;
+                s.spawn(move || {
+                    let mut succeeded = 0;
+                    for _ in 0..ATTEMPTS_PER_THREAD {
+                        if attempt(idx) {
+                            succeeded += 1;
+                        }
+                        busy(PAUSE);
+                    }
+                    succeeded
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .sum()
+    });
+    (succeeded, start.elapsed())
+}
+
+fn hold<G>(guard: Option<G>) -> bool {
+    match guard {
+        Some(guard) => {
+            busy(HOLD);
+            drop(guard);
+            true
+        }
+        None => false,
+    }
+}
+
+fn print_row(primitive: &str, mode: &str, (succeeded, elapsed): (usize, Duration)) {
+    let attempts = THREADS * ATTEMPTS_PER_THREAD;
+    println!(
+        "{:<12} {:<10} {:>9.1}% {:>14.0}",
+        primitive,
+        mode,
+        succeeded as f64 * 100.0 / attempts as f64,
+        attempts as f64 / elapsed.as_secs_f64(),
+    );
+}
+
+fn main() {
+    println!(
+        "{:<12} {:<10} {:>10} {:>14}",
+        "primitive", "mode", "succeeded", "attempts/s"
+    );
+    for (mode, spins) in MODES {
+        let sem = Semaphore::new(1);
+        let report = contend(|_| {
+            hold(match spins {
+                None => sem.try_acquire(1),
+                Some(spins) => sem.try_acquire_spin(1, spins),
+            })
+        });
+        print_row("semaphore", mode, report);
+    }
+    for (mode, spins) in MODES {
+        let mutex = Mutex::new(());
+        let report = contend(|_| {
+            hold(match spins {
+                None => mutex.try_lock(),
+                Some(spins) => mutex.try_lock_spin(spins),
+            })
+        });
+        print_row("mutex", mode, report);
+    }
+    for (mode, spins) in MODES {
+        let rwlock = RwLock::new(());
+        let report = contend(|_| {
+            hold(match spins {
+                None => rwlock.try_write(),
+                Some(spins) => rwlock.try_write_spin(spins),
+            })
+        });
+        print_row("rwlock write", mode, report);
+    }
+    for (mode, spins) in MODES {
+        // readers only contend with writers, so half the threads write
+        let rwlock = RwLock::new(());
+        let report = contend(|idx| match (idx % 2, spins) {
+            (0, None) => hold(rwlock.try_write()),
+            (0, Some(spins)) => hold(rwlock.try_write_spin(spins)),
+            (_, None) => hold(rwlock.try_read()),
+            (_, Some(spins)) => hold(rwlock.try_read_spin(spins)),
+        });
+        print_row("rwlock mixed", mode, report);
+    }
+}
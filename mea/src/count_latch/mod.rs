@@ -0,0 +1,263 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A reusable countdown latch that can be rearmed for another round once it has been released.
+//!
+//! Like a [`Latch`], a [`CountLatch`] lets tasks wait until its count reaches zero. Unlike a
+//! `Latch`, it can be [`reset()`] to a new count and used again, which makes it a good fit for
+//! phase-based computations where every phase waits for a known number of events.
+//!
+//! Each round has a generation. A task that starts waiting in one round is released when that
+//! round ends, even if the latch is reset for the next round before the task gets to run again.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() {
+//! use std::sync::Arc;
+//!
+//! use mea::count_latch::CountLatch;
+//!
+//! let latch = Arc::new(CountLatch::new(2));
+//!
+//! for round in 0..2 {
+//!     for i in 0..2 {
+//!         let latch = latch.clone();
+//!         tokio::spawn(async move {
+//!             println!("Round {} task {} done", round, i);
+//!             latch.count_down();
+//!         });
+//!     }
+//!
+//!     latch.wait().await;
+//!     println!("Round {} completed", round);
+//!     latch.reset(2);
+//! }
+//! # }
+//! ```
+//!
+//! [`Latch`]: crate::latch::Latch
+//! [`reset()`]: CountLatch::reset
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::internal::Mutex;
+use crate::internal::WaitSet;
+
+#[cfg(test)]
+mod tests;
+
+/// A countdown latch that can be reset and reused.
+///
+/// See the [module level documentation](self) for more.
+#[derive(Debug)]
+pub struct CountLatch {
+    state: Mutex<CountLatchState>,
+}
+
+struct CountLatchState {
+    count: u32,
+    generation: usize,
+    waiters: WaitSet,
+}
+
+impl fmt::Debug for CountLatchState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountLatchState")
+            .field("count", &self.count)
+            .field("generation", &self.generation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CountLatchState {
+    /// Ends the current round, releasing every task waiting on it.
+    fn release(&mut self) {
+        self.generation += 1;
+        self.waiters.wake_all();
+    }
+}
+
+impl CountLatch {
+    /// Creates a new latch initialized with the given count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::count_latch::CountLatch;
+    ///
+    /// let latch = CountLatch::new(3);
+    /// assert_eq!(latch.current_count(), 3);
+    /// ```
+    pub const fn new(count: u32) -> Self {
+        Self {
+            state: Mutex::new(CountLatchState {
+                count,
+                generation: 0,
+                waiters: WaitSet::new(),
+            }),
+        }
+    }
+
+    /// Returns the count of the current round.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::count_latch::CountLatch;
+    ///
+    /// let latch = CountLatch::new(2);
+    /// latch.count_down();
+    /// assert_eq!(latch.current_count(), 1);
+    /// ```
+    pub fn current_count(&self) -> u32 {
+        self.state.lock().count
+    }
+
+    /// Decrements the count by one, releasing all waiting tasks if the count reaches zero.
+    ///
+    /// If the count is already zero, this method has no effect.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::count_latch::CountLatch;
+    ///
+    /// let latch = CountLatch::new(1);
+    /// latch.count_down();
+    /// latch.count_down(); // no effect
+    /// assert_eq!(latch.current_count(), 0);
+    /// ```
+    pub fn count_down(&self) {
+        let mut state = self.state.lock();
+        if state.count == 0 {
+            return;
+        }
+
+        state.count -= 1;
+        if state.count == 0 {
+            state.release();
+        }
+    }
+
+    /// Rearms the latch with a new count for another round.
+    ///
+    /// Tasks waiting on the current round are released before the new count takes effect, so
+    /// resetting a latch whose count has not yet reached zero ends that round early. Tasks that
+    /// call [`wait()`] afterward wait for the new round.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::count_latch::CountLatch;
+    ///
+    /// let latch = CountLatch::new(1);
+    /// latch.count_down();
+    /// latch.reset(3);
+    /// assert_eq!(latch.current_count(), 3);
+    /// ```
+    ///
+    /// [`wait()`]: CountLatch::wait
+    pub fn reset(&self, count: u32) {
+        let mut state = self.state.lock();
+        if state.count != 0 {
+            state.release();
+        }
+        state.count = count;
+    }
+
+    /// Waits for the count of the current round to reach zero.
+    ///
+    /// Returns immediately if the count is already zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::count_latch::CountLatch;
+    ///
+    /// let latch = Arc::new(CountLatch::new(1));
+    /// let latch2 = latch.clone();
+    ///
+    /// let handle = tokio::spawn(async move {
+    ///     latch2.wait().await;
+    /// });
+    ///
+    /// latch.count_down();
+    /// handle.await.unwrap();
+    /// # }
+    /// ```
+    pub async fn wait(&self) {
+        let generation = {
+            let state = self.state.lock();
+            if state.count == 0 {
+                return;
+            }
+            state.generation
+        };
+
+        let fut = CountLatchWait {
+            idx: None,
+            generation,
+            latch: self,
+        };
+        fut.await
+    }
+}
+
+/// A future returned by [`CountLatch::wait()`].
+///
+/// This future will complete when the round it started in has ended.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+struct CountLatchWait<'a> {
+    idx: Option<usize>,
+    generation: usize,
+    latch: &'a CountLatch,
+}
+
+impl fmt::Debug for CountLatchWait<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountLatchWait")
+            .field("generation", &self.generation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Future for CountLatchWait<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Self {
+            idx,
+            generation,
+            latch,
+        } = self.get_mut();
+
+        let mut state = latch.state.lock();
+        if *generation < state.generation {
+            Poll::Ready(())
+        } else {
+            state.waiters.register_waker(idx, cx);
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tokio_test::assert_pending;
+use tokio_test::assert_ready;
+use tokio_test::task::spawn;
+
+use crate::count_latch::CountLatch;
+
+#[test]
+fn two_rounds_release_their_own_waiters() {
+    let latch = CountLatch::new(2);
+
+    // round one
+    let mut w1 = spawn(latch.wait());
+    let mut w2 = spawn(latch.wait());
+    assert_pending!(w1.poll());
+    assert_pending!(w2.poll());
+    latch.count_down();
+    assert_pending!(w1.poll());
+    latch.count_down();
+    assert!(w1.is_woken());
+    assert!(w2.is_woken());
+
+    // rearm before round-one waiters get to run; they must not be stranded
+    latch.reset(1);
+    assert_ready!(w1.poll());
+    assert_ready!(w2.poll());
+
+    // round two
+    let mut w3 = spawn(latch.wait());
+    assert_pending!(w3.poll());
+    latch.count_down();
+    assert!(w3.is_woken());
+    assert_ready!(w3.poll());
+    assert_eq!(latch.current_count(), 0);
+}
+
+#[test]
+fn reset_ends_unfinished_round() {
+    let latch = CountLatch::new(2);
+    let mut w = spawn(latch.wait());
+    assert_pending!(w.poll());
+
+    latch.reset(3);
+    assert!(w.is_woken());
+    assert_ready!(w.poll());
+    assert_eq!(latch.current_count(), 3);
+
+    let mut w = spawn(latch.wait());
+    assert_pending!(w.poll());
+}
+
+#[test]
+fn zero_does_not_block() {
+    let latch = CountLatch::new(0);
+    assert_ready!(spawn(latch.wait()).poll());
+    latch.count_down();
+    assert_eq!(latch.current_count(), 0);
+}
@@ -0,0 +1,79 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::sync::OnceLock;
+use std::task::Waker;
+use std::thread;
+use std::thread::Thread;
+use std::time::Instant;
+
+use crate::internal::Mutex;
+
+/// The wakers to wake at their deadlines.
+static TIMERS: Mutex<Vec<(Instant, Waker)>> = Mutex::new(Vec::new());
+
+/// The thread waking the wakers in [`TIMERS`], spawned on first use.
+static THREAD: OnceLock<Thread> = OnceLock::new();
+
+/// Wakes `waker` once `deadline` has passed.
+///
+/// The crate is runtime-agnostic, so it has no timer of the runtime to rely on. Instead, a
+/// single thread shared by the whole process sleeps until the earliest deadline. It is only meant
+/// for rare, coarse deadlines, e.g., of expiring leases.
+pub(crate) fn wake_at(deadline: Instant, waker: &Waker) {
+    let mut timers = TIMERS.lock();
+    // a task polled again before the deadline need not be woken twice
+    if timers
+        .iter()
+        .any(|(d, w)| *d == deadline && w.will_wake(waker))
+    {
+        return;
+    }
+    timers.push((deadline, waker.clone()));
+    drop(timers);
+
+    THREAD
+        .get_or_init(|| {
+            thread::Builder::new()
+                .name("mea-alarm".to_string())
+                .spawn(run)
+                .expect("failed to spawn the alarm thread")
+                .thread()
+                .clone()
+        })
+        .unpark();
+}
+
+fn run() {
+    loop {
+        let now = Instant::now();
+        let mut timers = TIMERS.lock();
+        let (due, pending): (Vec<_>, Vec<_>) = mem::take(&mut *timers)
+            .into_iter()
+            .partition(|(d, _)| *d <= now);
+        *timers = pending;
+        let next = timers.iter().map(|(d, _)| *d).min();
+        drop(timers);
+
+        for (_, waker) in due {
+            waker.wake();
+        }
+        // new deadlines unpark the thread, and a spurious wakeup merely checks once more
+        match next {
+            Some(next) => thread::park_timeout(next.saturating_duration_since(Instant::now())),
+            None => thread::park(),
+        }
+    }
+}
@@ -12,6 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod alarm;
+pub(crate) use alarm::*;
+
+mod blocking;
+pub(crate) use blocking::*;
+
 mod countdown;
 pub(crate) use countdown::*;
 
@@ -19,8 +25,18 @@ mod mutex;
 pub(crate) use mutex::*;
 
 mod semaphore;
+#[cfg(feature = "unstable-internals")]
+pub use semaphore::Semaphore;
 pub(crate) use semaphore::*;
 
+pub(crate) mod sync;
+
+mod trace;
+pub(crate) use trace::*;
+
+mod spin;
+pub(crate) use spin::*;
+
 mod waitlist;
 pub(crate) use waitlist::*;
 
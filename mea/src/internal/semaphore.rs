@@ -13,50 +13,237 @@
 // limitations under the License.
 
 use std::future::Future;
+use std::ops::Deref;
 use std::pin::Pin;
-use std::sync::atomic::AtomicU32;
-use std::sync::atomic::Ordering;
-use std::sync::MutexGuard;
 use std::task::Context;
 use std::task::Poll;
 use std::task::Waker;
 
 use slab::Slab;
 
-use crate::internal::Mutex;
+use crate::internal::sync::atomic::AtomicBool;
+use crate::internal::sync::atomic::AtomicU32;
+use crate::internal::sync::atomic::AtomicUsize;
+use crate::internal::sync::atomic::Ordering;
+use crate::internal::sync::const_fn;
+use crate::internal::sync::Mutex;
+use crate::internal::sync::MutexGuard;
 use crate::internal::WaitList;
+use crate::internal::WakerSet;
 
 /// The internal semaphore that provides low-level async primitives.
+///
+/// With the `unstable-internals` feature, this type is exposed as `mea::internals::Semaphore`,
+/// with only the constructors [`new`], [`with_order`] and [`barging`], and [`available_permits`],
+/// [`is_closed`] and [`close`] public.
+///
+/// [`new`]: Semaphore::new
+/// [`with_order`]: Semaphore::with_order
+/// [`barging`]: Semaphore::barging
+/// [`available_permits`]: Semaphore::available_permits
+/// [`is_closed`]: Semaphore::is_closed
+/// [`close`]: Semaphore::close
 #[derive(Debug)]
-pub(crate) struct Semaphore {
+pub struct Semaphore {
     /// The current number of available permits in the semaphore.
     permits: AtomicU32,
+    /// Whether the semaphore is closed. Only set while holding the lock of `waiters`.
+    closed: AtomicBool,
     waiters: Mutex<WaitList<WaitNode>>,
+    /// The number of waiters linked in `waiters`, to be read without the lock. Only updated
+    /// while holding the lock of `waiters`.
+    queued: AtomicUsize,
+    /// The number of permits assigned to the waiters linked in `waiters`, to be read without the
+    /// lock. Only updated while holding the lock of `waiters`.
+    assigned: AtomicU32,
+    /// The number of live watchers of the available permits, to skip notifying when there are
+    /// none.
+    watched: AtomicUsize,
+    /// Tasks waiting for the number of available permits to change.
+    watchers: Mutex<WakerSet>,
+    /// Whether new waiters are queued ahead of the existing ones.
+    lifo: bool,
+    /// Whether released permits are left for anyone to take, instead of being handed to the
+    /// first waiter, which is only woken to race for them.
+    barging: bool,
+}
+
+/// An error returned when permits cannot be acquired from the semaphore.
+#[derive(Debug)]
+pub(crate) enum AcquireError {
+    /// The semaphore is closed.
+    Closed,
+    /// Too many tasks are already waiting for permits.
+    QueueFull,
 }
 
 #[derive(Debug)]
 struct WaitNode {
+    /// The number of permits the waiter still needs.
     permits: u32,
+    /// The number of permits already assigned to the waiter.
+    assigned: u32,
     waker: Option<Waker>,
 }
 
 impl Semaphore {
-    pub(crate) fn new(permits: u32) -> Self {
-        Self {
-            permits: AtomicU32::new(permits),
-            waiters: Mutex::new(WaitList::new()),
+    /// The maximum number of available permits.
+    pub(crate) const MAX_PERMITS: u32 = u32::MAX;
+
+    const_fn! {
+        /// Creates a new semaphore with the given number of permits.
+        pub fn new(permits: u32) -> Self {
+            Self::with_order(permits, false)
+        }
+    }
+
+    const_fn! {
+        /// Creates a new semaphore with the given number of permits, which hands out permits to
+        /// the most recent waiter first if `lifo` is `true`.
+        pub fn with_order(permits: u32, lifo: bool) -> Self {
+            Self::with_options(permits, lifo, false)
+        }
+    }
+
+    const_fn! {
+        /// Creates a new semaphore with the given number of permits, which lets new acquirers
+        /// take released permits ahead of the waiters.
+        pub fn barging(permits: u32) -> Self {
+            Self::with_options(permits, false, true)
+        }
+    }
+
+    const_fn! {
+        fn with_options(permits: u32, lifo: bool, barging: bool) -> Self {
+            Self {
+                permits: AtomicU32::new(permits),
+                closed: AtomicBool::new(false),
+                waiters: Mutex::new(WaitList::new()),
+                queued: AtomicUsize::new(0),
+                assigned: AtomicU32::new(0),
+                watched: AtomicUsize::new(0),
+                watchers: Mutex::new(WakerSet::new()),
+                lifo,
+                barging,
+            }
+        }
+    }
+
+    /// Registers a watcher of the available permits, see [`Semaphore::poll_available_changed`].
+    pub(crate) fn watch(&self) {
+        self.watched.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Deregisters a watcher of the available permits.
+    pub(crate) fn unwatch(&self, key: Option<usize>) {
+        if let Some(key) = key {
+            self.watchers.lock().cancel(key);
+        }
+        self.watched.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Polls for the number of available permits to differ from `last`.
+    ///
+    /// Intermediate values may be skipped: only the latest number is returned. `key` tracks the
+    /// registration of the waker, and must be passed to [`Semaphore::unwatch`] on drop.
+    pub(crate) fn poll_available_changed(
+        &self,
+        last: &mut Option<u32>,
+        key: &mut Option<usize>,
+        cx: &mut Context<'_>,
+    ) -> Poll<u32> {
+        // permits are changed before notifying under this lock, so reading them under the lock
+        // cannot miss a change
+        let mut watchers = self.watchers.lock();
+        let current = self.available_permits();
+        if *last != Some(current) {
+            if let Some(key) = key.take() {
+                watchers.cancel(key);
+            }
+            *last = Some(current);
+            return Poll::Ready(current);
+        }
+
+        match *key {
+            // notified, but the number changed back in the meantime
+            Some(k) if watchers.remove_if_notified(k, cx) => *key = Some(watchers.insert(cx)),
+            Some(_) => {}
+            None => *key = Some(watchers.insert(cx)),
+        }
+        Poll::Pending
+    }
+
+    /// Notifies the watchers that the number of available permits has changed.
+    fn notify_watchers(&self) {
+        if self.watched.load(Ordering::SeqCst) > 0 {
+            self.watchers.lock().notify_all();
         }
     }
 
     /// Returns the current number of available permits.
-    pub(crate) fn available_permits(&self) -> u32 {
+    pub fn available_permits(&self) -> u32 {
         self.permits.load(Ordering::Acquire)
     }
 
+    /// Returns the number of tasks waiting for permits.
+    ///
+    /// The number is read without locking the wait list, so it may be slightly stale.
+    pub(crate) fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of permits assigned to tasks that are still waiting for more.
+    ///
+    /// Such permits are neither available nor held. The number is read without locking the wait
+    /// list, so it may be slightly stale.
+    pub(crate) fn assigned(&self) -> u32 {
+        self.assigned.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the semaphore is closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Closes the semaphore, waking up all pending waiters.
+    ///
+    /// Pending and future acquisitions fail, while permits already acquired can still be released.
+    pub fn close(&self) {
+        let mut waiters = self.waiters.lock();
+        self.closed.store(true, Ordering::Release);
+        waiters.for_each(|node| {
+            if let Some(waker) = node.waker.take() {
+                waker.wake();
+            }
+        });
+    }
+
+    /// Wakes all the waiters without handing them permits, so that they poll once more.
+    pub(crate) fn wake_waiters(&self) {
+        if self.queued() == 0 {
+            return;
+        }
+        let mut wakers = Vec::new();
+        self.waiters
+            .lock()
+            .for_each(|node| wakers.extend(node.waker.take()));
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
     /// Tries to acquire `n` permits from the semaphore.
     ///
-    /// Returns `true` if the permits were acquired, `false` otherwise.
+    /// Returns `true` if the permits were acquired, `false` otherwise, including when the
+    /// semaphore is closed. Zero permits are always acquired.
     pub(crate) fn try_acquire(&self, n: u32) -> bool {
+        if n == 0 {
+            return true;
+        }
+        if self.is_closed() {
+            return false;
+        }
+
         let mut current = self.permits.load(Ordering::Acquire);
         loop {
             if current < n {
@@ -68,12 +255,30 @@ impl Semaphore {
                 .permits
                 .compare_exchange(current, next, Ordering::AcqRel, Ordering::Acquire)
             {
-                Ok(_) => return true,
+                Ok(_) => {
+                    self.notify_watchers();
+                    return true;
+                }
                 Err(actual) => current = actual,
             }
         }
     }
 
+    /// Acquires all the available permits at once, and returns the number of them.
+    ///
+    /// Returns zero if the semaphore is closed.
+    pub(crate) fn try_acquire_all(&self) -> u32 {
+        if self.is_closed() {
+            return 0;
+        }
+
+        let acquired = self.permits.swap(0, Ordering::AcqRel);
+        if acquired > 0 {
+            self.notify_watchers();
+        }
+        acquired
+    }
+
     /// Decrease a semaphore's permits by a maximum of `n`.
     ///
     /// Return the number of permits that were actually reduced.
@@ -91,16 +296,38 @@ impl Semaphore {
                 Ordering::AcqRel,
                 Ordering::Acquire,
             ) {
-                Ok(_) => return n.min(current),
+                Ok(_) => {
+                    if current > 0 {
+                        self.notify_watchers();
+                    }
+                    return n.min(current);
+                }
                 Err(actual) => current = actual,
             }
         }
     }
 
     /// Acquires `n` permits from the semaphore.
-    pub(crate) async fn acquire(&self, n: u32) {
+    ///
+    /// Returns `Err(AcquireError::Closed)` if the semaphore is closed before the permits are
+    /// acquired.
+    pub(crate) async fn acquire(&self, n: u32) -> Result<(), AcquireError> {
+        Acquire::new(self, n).await
+    }
+
+    /// Acquires `n` permits from the semaphore, unless `max_queue` tasks are already waiting.
+    ///
+    /// Returns `Err(AcquireError::QueueFull)` without waiting if the permits are not immediately
+    /// available and the wait queue is full.
+    pub(crate) async fn acquire_bounded(
+        &self,
+        n: u32,
+        max_queue: usize,
+    ) -> Result<(), AcquireError> {
         let fut = Acquire {
             permits: n,
+            max_queue: Some(max_queue),
+            front: false,
             index: None,
             semaphore: self,
             done: false,
@@ -108,6 +335,18 @@ impl Semaphore {
         fut.await
     }
 
+    /// Acquires `n` permits from the semaphore, waiting ahead of the tasks already waiting.
+    ///
+    /// This suits a task that already holds permits, and must not wait behind a task that waits
+    /// for them.
+    pub(crate) async fn acquire_front(&self, n: u32) -> Result<(), AcquireError> {
+        let fut = Acquire {
+            front: true,
+            ..Acquire::new(self, n)
+        };
+        fut.await
+    }
+
     /// Adds `n` new permits to the semaphore.
     pub(crate) fn release(&self, n: u32) {
         if n != 0 {
@@ -115,21 +354,46 @@ impl Semaphore {
         }
     }
 
+    /// Adds `n` new permits to the semaphore, or returns `false` without adding any if the number
+    /// of available permits would overflow.
+    pub(crate) fn try_release(&self, n: u32) -> bool {
+        if n != 0 {
+            let waiters = self.waiters.lock();
+            // permits are only added under the lock, so they cannot overflow after the check
+            if n > Self::MAX_PERMITS - self.available_permits() {
+                return false;
+            }
+            self.insert_permits_with_lock(n, waiters);
+        }
+        true
+    }
+
     fn insert_permits_with_lock(&self, mut rem: u32, waiters: MutexGuard<'_, WaitList<WaitNode>>) {
+        if self.barging {
+            self.insert_permits_barging(rem, waiters);
+            return;
+        }
+
         const NUM_WAKER: usize = 32;
         let mut wakers = Slab::with_capacity(NUM_WAKER);
 
         let mut lock = Some(waiters);
         while rem > 0 {
             let mut waiters = lock.take().unwrap_or_else(|| self.waiters.lock());
-            while wakers.len() < NUM_WAKER {
+            // pending waiters of a closed semaphore are going to fail
+            let closed = self.is_closed();
+            let (mut assigned, mut unassigned) = (0, 0);
+            while !closed && wakers.len() < NUM_WAKER {
                 match waiters.remove_first_waiter(|node| {
                     if node.permits <= rem {
                         rem -= node.permits;
                         node.permits = 0;
+                        unassigned += node.assigned;
                         true
                     } else {
                         node.permits -= rem;
+                        node.assigned += rem;
+                        assigned += rem;
                         rem = 0;
                         false
                     }
@@ -142,14 +406,21 @@ impl Semaphore {
                     }
                 }
             }
+            self.queued.store(waiters.len(), Ordering::Relaxed);
+            if assigned != unassigned {
+                let current = self.assigned.load(Ordering::Relaxed);
+                self.assigned
+                    .store(current + assigned - unassigned, Ordering::Relaxed);
+            }
 
-            if rem > 0 && waiters.is_empty() {
+            if rem > 0 && (closed || waiters.is_empty()) {
                 let permits = rem;
                 let prev = self.permits.fetch_add(permits, Ordering::Release);
                 assert!(
                     prev.checked_add(permits).is_some(),
                     "number of added permits ({permits}) would overflow u32::MAX (prev: {prev})"
                 );
+                self.notify_watchers();
                 rem = 0;
             }
 
@@ -159,27 +430,106 @@ impl Semaphore {
             }
         }
     }
+
+    /// Adds `n` permits to be taken by whoever comes first, and wakes the first waiter to race
+    /// for them.
+    ///
+    /// Permits are added while holding the lock, so that a waiter failing to take them under the
+    /// lock is always woken later.
+    fn insert_permits_barging(&self, n: u32, mut waiters: MutexGuard<'_, WaitList<WaitNode>>) {
+        let prev = self.permits.fetch_add(n, Ordering::Release);
+        assert!(
+            prev.checked_add(n).is_some(),
+            "number of added permits ({n}) would overflow u32::MAX (prev: {prev})"
+        );
+        self.notify_watchers();
+        let waker = self.take_first_waker(&mut waiters);
+        drop(waiters);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Takes the waker of the first waiter without unlinking it, if permits are available for it
+    /// to race for.
+    fn take_first_waker(&self, waiters: &mut WaitList<WaitNode>) -> Option<Waker> {
+        let mut waker = None;
+        if self.available_permits() > 0 {
+            waiters.remove_first_waiter(|node| {
+                waker = node.waker.take();
+                false
+            });
+        }
+        waker
+    }
 }
 
+/// A future acquiring permits from the semaphore referenced by `S`.
+///
+/// `S` is a reference to the semaphore, or an owning handle of it. Dropping the future before it
+/// completes removes the waiter from the wait list, and returns the permits assigned to it.
 #[derive(Debug)]
-pub(crate) struct Acquire<'a> {
+pub(crate) struct Acquire<S: Deref<Target = Semaphore>> {
     permits: u32,
+    /// The maximum number of waiters to queue behind, if any.
+    max_queue: Option<usize>,
+    /// Whether to wait ahead of the existing waiters, regardless of the order of the semaphore.
+    front: bool,
     index: Option<usize>,
-    semaphore: &'a Semaphore,
+    semaphore: S,
     done: bool,
 }
 
-impl Drop for Acquire<'_> {
-    fn drop(&mut self) {
-        if let Some(index) = self.index {
+impl<S: Deref<Target = Semaphore>> Acquire<S> {
+    /// Creates a future acquiring `permits` permits from `semaphore`.
+    pub(crate) fn new(semaphore: S, permits: u32) -> Self {
+        Self {
+            permits,
+            max_queue: None,
+            front: false,
+            index: None,
+            semaphore,
+            done: false,
+        }
+    }
+
+    /// Returns the semaphore handle of this future.
+    pub(crate) fn semaphore(&self) -> &S {
+        &self.semaphore
+    }
+
+    /// Removes the waiter from the wait list, and returns the permits assigned to it.
+    fn cancel(&mut self) {
+        if let Some(index) = self.index.take() {
             let mut waiters = self.semaphore.waiters.lock();
-            let mut acquired = 0;
-            waiters.remove_waiter(index, |node| {
-                acquired = node.permits;
-                node.permits = 0;
-                true
+            let mut remaining = 0;
+            let mut assigned = 0;
+            waiters.with_mut(index, |node| {
+                remaining = node.permits;
+                assigned = node.assigned;
+                false
             });
+            // a waiter is unlinked from the list once all its permits are assigned
+            if remaining > 0 {
+                waiters.remove_waiter(index, |_| true);
+                self.semaphore
+                    .queued
+                    .store(waiters.len(), Ordering::Relaxed);
+                self.semaphore
+                    .assigned
+                    .fetch_sub(assigned, Ordering::Relaxed);
+            }
             waiters.with_mut(index, |_| true); // drop
+            if self.semaphore.barging {
+                // the waiter may have been woken to race for permits, so pass that on
+                let waker = self.semaphore.take_first_waker(&mut waiters);
+                drop(waiters);
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+                return;
+            }
+            let acquired = self.permits - remaining;
             if acquired > 0 {
                 self.semaphore.insert_permits_with_lock(acquired, waiters);
             }
@@ -187,27 +537,78 @@ impl Drop for Acquire<'_> {
     }
 }
 
-impl Future for Acquire<'_> {
-    type Output = ();
+impl<S: Deref<Target = Semaphore>> Drop for Acquire<S> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl<S: Deref<Target = Semaphore> + Unpin> Future for Acquire<S> {
+    type Output = Result<(), AcquireError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
         let Self {
             permits,
+            max_queue,
+            front,
             index,
             semaphore,
             done,
-        } = self.get_mut();
+        } = this;
+        let semaphore: &Semaphore = semaphore;
 
         if *done {
-            return Poll::Ready(());
+            return Poll::Ready(Ok(()));
         }
 
         match index {
+            Some(idx) if semaphore.barging => {
+                let mut waiters = semaphore.waiters.lock();
+                if semaphore.is_closed() {
+                    drop(waiters);
+                    this.cancel();
+                    this.done = true;
+                    return Poll::Ready(Err(AcquireError::Closed));
+                }
+
+                // permits are added while holding the lock, so either they are taken here, or
+                // the waker registered below is woken later
+                if semaphore.try_acquire(*permits) {
+                    waiters.remove_waiter(*idx, |_| true);
+                    waiters.with_mut(*idx, |_| true); // drop
+                    semaphore.queued.store(waiters.len(), Ordering::Relaxed);
+                    *index = None;
+                    *done = true;
+                    // leave what remains to the next waiter
+                    let waker = semaphore.take_first_waker(&mut waiters);
+                    drop(waiters);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                    return Poll::Ready(Ok(()));
+                }
+
+                waiters.with_mut(*idx, |node| {
+                    let update_waker = node
+                        .waker
+                        .as_ref()
+                        .map_or(true, |w| !w.will_wake(cx.waker()));
+                    if update_waker {
+                        node.waker = Some(cx.waker().clone());
+                    }
+                    false
+                });
+            }
             Some(idx) => {
                 let mut waiters = semaphore.waiters.lock();
                 let mut ready = false;
+                let mut closed = false;
                 waiters.with_mut(*idx, |node| {
-                    if node.permits > 0 {
+                    if node.permits > 0 && semaphore.is_closed() {
+                        closed = true;
+                        false
+                    } else if node.permits > 0 {
                         let update_waker = node
                             .waker
                             .as_ref()
@@ -225,12 +626,23 @@ impl Future for Acquire<'_> {
                 if ready {
                     *index = None;
                     *done = true;
-                    return Poll::Ready(());
+                    return Poll::Ready(Ok(()));
+                }
+
+                if closed {
+                    drop(waiters);
+                    this.cancel();
+                    this.done = true;
+                    return Poll::Ready(Err(AcquireError::Closed));
                 }
             }
             None => {
                 // not yet enqueued
                 let needed = *permits;
+                if needed > 0 && semaphore.is_closed() {
+                    *done = true;
+                    return Poll::Ready(Err(AcquireError::Closed));
+                }
 
                 let mut acquired = 0;
                 let mut current = semaphore.permits.load(Ordering::Acquire);
@@ -242,6 +654,10 @@ impl Future for Acquire<'_> {
                     let (next, acq) = if total >= needed {
                         let next = current - (needed - acquired);
                         (next, needed - acquired)
+                    } else if semaphore.barging {
+                        // waiters are never handed permits partially
+                        remaining = needed;
+                        (current, 0)
                     } else {
                         remaining = (needed - acquired) - current;
                         (0, current)
@@ -265,10 +681,13 @@ impl Future for Acquire<'_> {
                         Ordering::Acquire,
                     ) {
                         Ok(_) => {
+                            if acq > 0 {
+                                semaphore.notify_watchers();
+                            }
                             acquired += acq;
                             if remaining == 0 {
                                 *done = true;
-                                return Poll::Ready(());
+                                return Poll::Ready(Ok(()));
                             }
                             break lock.expect("lock not acquired");
                         }
@@ -276,13 +695,55 @@ impl Future for Acquire<'_> {
                     }
                 };
 
-                waiters.register_waiter(index, |node| match node {
+                // the semaphore may be closed before we took the lock
+                if semaphore.is_closed() {
+                    *done = true;
+                    if acquired > 0 {
+                        semaphore.insert_permits_with_lock(acquired, waiters);
+                    }
+                    return Poll::Ready(Err(AcquireError::Closed));
+                }
+
+                // the queue length is checked under the lock, so that concurrent acquirers
+                // cannot both slip past the limit
+                if max_queue.is_some_and(|max| waiters.len() >= max) {
+                    *done = true;
+                    if acquired > 0 {
+                        semaphore.insert_permits_with_lock(acquired, waiters);
+                    }
+                    return Poll::Ready(Err(AcquireError::QueueFull));
+                }
+
+                // Only the head is handed permits partially. A new head would leave the permits
+                // of the old one stranded, and the two could wait for each other forever, so take
+                // them back to hand out again.
+                let front = *front || semaphore.lifo;
+                let mut reclaimed = 0;
+                if front {
+                    waiters.remove_first_waiter(|node| {
+                        reclaimed = node.assigned;
+                        node.permits += node.assigned;
+                        node.assigned = 0;
+                        false
+                    });
+                }
+
+                waiters.register_waiter(index, front, |node| match node {
                     None => Some(WaitNode {
                         permits: needed - acquired,
+                        assigned: acquired,
                         waker: Some(cx.waker().clone()),
                     }),
                     Some(node) => unreachable!("unexpected node: {:?}", node),
                 });
+                semaphore.queued.store(waiters.len(), Ordering::Relaxed);
+                let current = semaphore.assigned.load(Ordering::Relaxed);
+                semaphore
+                    .assigned
+                    .store(current + acquired - reclaimed, Ordering::Relaxed);
+                if reclaimed > 0 {
+                    semaphore.insert_permits_with_lock(reclaimed, waiters);
+                }
             }
         };
 
@@ -0,0 +1,77 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Instrumentation with the `tracing` crate, compiled out unless the `tracing` feature is enabled.
+//!
+//! Events are emitted from the public primitives, never while the internal semaphore's lock is
+//! held.
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+use crate::internal::Semaphore;
+
+/// Traces a wait for permits, from the start of the wait until the permits are granted.
+pub(crate) struct AcquireTrace {
+    #[cfg(feature = "tracing")]
+    primitive: &'static str,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+}
+
+impl AcquireTrace {
+    /// Emits the wait-start event for acquiring `permits` permits from `s`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn start(primitive: &'static str, s: &Semaphore, permits: u32) -> Self {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            primitive,
+            permits,
+            available = s.available_permits(),
+            "acquire wait start"
+        );
+
+        Self {
+            #[cfg(feature = "tracing")]
+            primitive,
+            #[cfg(feature = "tracing")]
+            start: Instant::now(),
+        }
+    }
+
+    /// Emits the granted event, with the time spent waiting.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn granted(self, s: &Semaphore, permits: u32) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            primitive = self.primitive,
+            permits,
+            available = s.available_permits(),
+            wait = ?self.start.elapsed(),
+            "acquire granted"
+        );
+    }
+}
+
+/// Emits the release event after `permits` permits have been released to `s`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn trace_release(primitive: &'static str, s: &Semaphore, permits: u32) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(
+        primitive,
+        permits,
+        available = s.available_permits(),
+        "release"
+    );
+}
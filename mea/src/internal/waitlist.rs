@@ -18,10 +18,15 @@ use slab::Slab;
 ///
 /// * `guard`'s `next` points to the first node (regular head).
 /// * `guard`'s `prev` points to the last node (regular tail).
+///
+/// The guard node is inserted on the first registration, so that an empty list can be created in
+/// a `const` context.
 #[derive(Debug)]
 pub(crate) struct WaitList<T> {
     guard: usize,
     nodes: Slab<Node<T>>,
+    /// The number of nodes linked in the list.
+    len: usize,
 }
 
 #[derive(Debug)]
@@ -32,36 +37,42 @@ struct Node<T> {
 }
 
 impl<T> WaitList<T> {
-    pub(crate) fn new() -> Self {
-        let mut nodes = Slab::new();
-        let first = nodes.vacant_entry();
-        let guard = first.key();
-        first.insert(Node {
-            prev: guard,
-            next: guard,
-            stat: None,
-        });
-        Self { guard, nodes }
+    pub(crate) const fn new() -> Self {
+        Self {
+            // the first key of an empty slab
+            guard: 0,
+            nodes: Slab::new(),
+            len: 0,
+        }
     }
 
-    /// Registers a waiter to the tail of the wait list.
+    /// Registers a waiter to the tail of the wait list, or to the head if `front` is `true`.
     pub(crate) fn register_waiter(
         &mut self,
         idx: &mut Option<usize>,
+        front: bool,
         f: impl FnOnce(Option<&T>) -> Option<T>,
     ) {
         match *idx {
             None => {
+                if self.nodes.is_empty() {
+                    let guard = self.nodes.insert(Node {
+                        prev: self.guard,
+                        next: self.guard,
+                        stat: None,
+                    });
+                    debug_assert_eq!(guard, self.guard);
+                }
                 let stat = f(None);
-                let prev_tail = self.nodes[self.guard].prev;
-                let new_node = Node {
-                    prev: prev_tail,
-                    next: self.guard,
-                    stat,
+                let (prev, next) = if front {
+                    (self.guard, self.nodes[self.guard].next)
+                } else {
+                    (self.nodes[self.guard].prev, self.guard)
                 };
-                let new_key = self.nodes.insert(new_node);
-                self.nodes[self.guard].prev = new_key;
-                self.nodes[prev_tail].next = new_key;
+                let new_key = self.nodes.insert(Node { prev, next, stat });
+                self.nodes[prev].next = new_key;
+                self.nodes[next].prev = new_key;
+                self.len += 1;
                 *idx = Some(new_key);
             }
             Some(key) => {
@@ -90,6 +101,7 @@ impl<T> WaitList<T> {
             let next = self.nodes[idx].next;
             self.nodes[prev].next = next;
             self.nodes[next].prev = prev;
+            self.len -= 1;
             Some(retrieve_stat(&mut self.nodes[idx]))
         } else {
             None
@@ -98,17 +110,34 @@ impl<T> WaitList<T> {
 
     /// Removes the first waiter from the wait list.
     pub(crate) fn remove_first_waiter(&mut self, f: impl FnOnce(&mut T) -> bool) -> Option<&mut T> {
-        let first = self.nodes[self.guard].next;
-        if first != self.guard {
-            self.remove_waiter(first, f)
-        } else {
-            None
+        if self.is_empty() {
+            return None;
         }
+        let first = self.nodes[self.guard].next;
+        self.remove_waiter(first, f)
     }
 
     /// Returns `true` if the wait list is empty.
     pub(crate) fn is_empty(&self) -> bool {
-        self.nodes[self.guard].next == self.guard
+        self.len == 0
+    }
+
+    /// Returns the number of waiters in the wait list.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Calls `f` on each waiter in the wait list, from head to tail.
+    pub(crate) fn for_each(&mut self, mut f: impl FnMut(&mut T)) {
+        if self.is_empty() {
+            return;
+        }
+        let mut idx = self.nodes[self.guard].next;
+        while idx != self.guard {
+            let node = &mut self.nodes[idx];
+            f(node.stat.as_mut().unwrap());
+            idx = node.next;
+        }
     }
 
     pub(crate) fn with_mut(&mut self, idx: usize, drop: impl FnOnce(&mut T) -> bool) {
@@ -17,13 +17,14 @@ use std::task::Waker;
 
 use slab::Slab;
 
+#[derive(Debug)]
 pub(crate) struct WakerSet {
     entries: Slab<Option<Waker>>,
     notifiable: usize,
 }
 
 impl WakerSet {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             entries: Slab::new(),
             notifiable: 0,
@@ -0,0 +1,222 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-key synchronization primitives.
+//!
+//! A [`KeyedSemaphore`] maintains an independent [`Semaphore`] for each key, which is created
+//! lazily on first use and reclaimed once nobody holds or waits for its permits. This is useful
+//! for per-tenant or per-resource concurrency limits where the set of keys is not known upfront.
+//!
+//! A [`KeyedOnceCell`] initializes a value at most once per key, with concurrent initializations
+//! of the same key serialized by a gate from a `KeyedSemaphore`.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() {
+//! use mea::keyed::KeyedSemaphore;
+//!
+//! // each tenant can run at most 2 requests concurrently
+//! let limiter = KeyedSemaphore::new(2);
+//!
+//! let a1 = limiter.acquire("tenant-a", 1).await;
+//! let a2 = limiter.acquire("tenant-a", 1).await;
+//! assert!(limiter.try_acquire("tenant-a", 1).is_none());
+//!
+//! // other tenants are not affected
+//! let b1 = limiter.acquire("tenant-b", 1).await;
+//! assert_eq!(limiter.len(), 2);
+//!
+//! drop((a1, a2, b1));
+//! // idle keys are reclaimed
+//! assert!(limiter.is_empty());
+//! # }
+//! ```
+//!
+//! [`Semaphore`]: crate::semaphore::Semaphore
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::internal::Mutex;
+use crate::semaphore::OwnedSemaphorePermit;
+use crate::semaphore::Semaphore;
+
+mod once_cell;
+pub use once_cell::KeyedOnceCell;
+
+#[cfg(test)]
+mod tests;
+
+/// A set of semaphores, one per key, created on demand.
+///
+/// See the [module level documentation](self) for more.
+pub struct KeyedSemaphore<K> {
+    permits: u32,
+    sems: Mutex<HashMap<K, Arc<Semaphore>>>,
+}
+
+impl<K> fmt::Debug for KeyedSemaphore<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedSemaphore")
+            .field("permits", &self.permits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K: Hash + Eq + Clone> KeyedSemaphore<K> {
+    /// Creates a new keyed semaphore where each key has the given number of permits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::keyed::KeyedSemaphore;
+    ///
+    /// let limiter = KeyedSemaphore::<String>::new(5);
+    /// ```
+    pub fn new(permits: u32) -> Self {
+        Self {
+            permits,
+            sems: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the number of keys that currently have a semaphore.
+    ///
+    /// This method is typically used for debugging and testing purposes.
+    pub fn len(&self) -> usize {
+        self.sems.lock().len()
+    }
+
+    /// Returns `true` if no key currently has a semaphore.
+    ///
+    /// This method is typically used for debugging and testing purposes.
+    pub fn is_empty(&self) -> bool {
+        self.sems.lock().is_empty()
+    }
+
+    /// Attempts to acquire `n` permits for `key` without blocking.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::keyed::KeyedSemaphore;
+    ///
+    /// let limiter = KeyedSemaphore::new(1);
+    /// let permit = limiter.try_acquire("key", 1).unwrap();
+    /// assert!(limiter.try_acquire("key", 1).is_none());
+    /// ```
+    pub fn try_acquire(&self, key: K, permits: u32) -> Option<KeyedSemaphorePermit<'_, K>> {
+        let handle = self.handle(key);
+        let permit = handle.sem.clone().try_acquire_owned(permits)?;
+        Some(KeyedSemaphorePermit { permit, handle })
+    }
+
+    /// Acquires `n` permits for `key`.
+    ///
+    /// If the permits are not immediately available, this method will wait until they become
+    /// available. Returns a [`KeyedSemaphorePermit`] that will release the permits when dropped.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire` makes you lose your place in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::keyed::KeyedSemaphore;
+    ///
+    /// let limiter = KeyedSemaphore::new(1);
+    /// let permit = limiter.acquire("key", 1).await;
+    /// # }
+    /// ```
+    pub async fn acquire(&self, key: K, permits: u32) -> KeyedSemaphorePermit<'_, K> {
+        let handle = self.handle(key);
+        let permit = handle.sem.clone().acquire_owned(permits).await;
+        KeyedSemaphorePermit { permit, handle }
+    }
+
+    fn handle(&self, key: K) -> KeyHandle<'_, K> {
+        let mut sems = self.sems.lock();
+        let sem = sems
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.permits)))
+            .clone();
+        KeyHandle {
+            keyed: self,
+            key,
+            sem,
+        }
+    }
+}
+
+/// A reference to the semaphore of a key, which reclaims the semaphore when the last reference
+/// is dropped.
+struct KeyHandle<'a, K: Hash + Eq + Clone> {
+    keyed: &'a KeyedSemaphore<K>,
+    key: K,
+    sem: Arc<Semaphore>,
+}
+
+impl<K: Hash + Eq + Clone> Drop for KeyHandle<'_, K> {
+    fn drop(&mut self) {
+        let mut sems = self.keyed.sems.lock();
+        // handles are only created under the lock, so if the map and this handle are the last
+        // two references, nobody else is holding or waiting for permits of this key
+        if Arc::strong_count(&self.sem) == 2 {
+            sems.remove(&self.key);
+        }
+    }
+}
+
+/// A permit from a [`KeyedSemaphore`].
+///
+/// This type is created by the [`acquire`] and [`try_acquire`] methods on [`KeyedSemaphore`].
+/// When the permit is dropped, the permits will be returned to the semaphore of its key.
+///
+/// [`acquire`]: KeyedSemaphore::acquire
+/// [`try_acquire`]: KeyedSemaphore::try_acquire
+#[must_use = "permits are released immediately when dropped"]
+pub struct KeyedSemaphorePermit<'a, K: Hash + Eq + Clone> {
+    // the permit must be dropped before the handle checks whether the key is idle
+    permit: OwnedSemaphorePermit,
+    handle: KeyHandle<'a, K>,
+}
+
+impl<K: Hash + Eq + Clone> KeyedSemaphorePermit<'_, K> {
+    /// Returns the key this permit is acquired for.
+    pub fn key(&self) -> &K {
+        &self.handle.key
+    }
+
+    /// Returns the number of permits this permit holds.
+    pub fn permits(&self) -> u32 {
+        self.permit.permits()
+    }
+}
+
+impl<K: Hash + Eq + Clone + fmt::Debug> fmt::Debug for KeyedSemaphorePermit<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedSemaphorePermit")
+            .field("key", self.key())
+            .field("permits", &self.permits())
+            .finish()
+    }
+}
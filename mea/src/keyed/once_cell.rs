@@ -0,0 +1,183 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::Hash;
+
+use crate::internal::Mutex;
+use crate::keyed::KeyedSemaphore;
+
+/// A map of values, each initialized at most once per key.
+///
+/// Concurrent initializations of the same key are serialized by a per-key gate: the first caller
+/// runs the initializer, and the others wait for it and then get the initialized value.
+/// Different keys are initialized concurrently. If an initializer fails or is cancelled, the
+/// next waiting caller runs its own initializer.
+///
+/// # Examples
+///
+/// ```
+/// # #[tokio::main]
+/// # async fn main() {
+/// use mea::keyed::KeyedOnceCell;
+///
+/// let files = KeyedOnceCell::new();
+/// let v = files
+///     .get_or_init("a.txt", || async { String::from("contents of a") })
+///     .await;
+/// assert_eq!(v, "contents of a");
+///
+/// // initialized values are returned without running the initializer again
+/// let v = files
+///     .get_or_init("a.txt", || async { unreachable!() })
+///     .await;
+/// assert_eq!(v, "contents of a");
+/// # }
+/// ```
+pub struct KeyedOnceCell<K, V> {
+    values: Mutex<HashMap<K, V>>,
+    gates: KeyedSemaphore<K>,
+}
+
+impl<K, V> fmt::Debug for KeyedOnceCell<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedOnceCell").finish_non_exhaustive()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for KeyedOnceCell<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> KeyedOnceCell<K, V> {
+    /// Creates a new, empty map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::keyed::KeyedOnceCell;
+    ///
+    /// let cell = KeyedOnceCell::<String, u64>::new();
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            values: Mutex::new(HashMap::new()),
+            gates: KeyedSemaphore::new(1),
+        }
+    }
+
+    /// Returns the value of `key` if it has been initialized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::keyed::KeyedOnceCell;
+    ///
+    /// let cell = KeyedOnceCell::new();
+    /// assert_eq!(cell.get(&"key"), None);
+    /// cell.get_or_init("key", || async { 1 }).await;
+    /// assert_eq!(cell.get(&"key"), Some(1));
+    /// # }
+    /// ```
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.values.lock().get(key).cloned()
+    }
+
+    /// Returns the value of `key`, initializing it with `f` if it has not been initialized.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. Cancelling a call that is running its initializer lets the
+    /// next waiting caller for the key run its own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::keyed::KeyedOnceCell;
+    ///
+    /// let cell = KeyedOnceCell::new();
+    /// let v = cell.get_or_init(1, || async { "one" }).await;
+    /// assert_eq!(v, "one");
+    /// # }
+    /// ```
+    pub async fn get_or_init<F, Fut>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let init = || async { Ok::<_, std::convert::Infallible>(f().await) };
+        match self.get_or_try_init(key, init).await {
+            Ok(v) => v,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Returns the value of `key`, initializing it with `f` if it has not been initialized.
+    ///
+    /// If `f` returns an error, the key stays uninitialized, the error is returned, and the next
+    /// waiting caller for the key runs its own initializer.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe. Cancelling a call that is running its initializer lets the
+    /// next waiting caller for the key run its own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::keyed::KeyedOnceCell;
+    ///
+    /// let cell = KeyedOnceCell::new();
+    /// let err = cell
+    ///     .get_or_try_init("key", || async { Err("failed") })
+    ///     .await;
+    /// assert_eq!(err, Err("failed"));
+    ///
+    /// let v = cell
+    ///     .get_or_try_init("key", || async { Ok::<_, &str>(2) })
+    ///     .await;
+    /// assert_eq!(v, Ok(2));
+    /// # }
+    /// ```
+    pub async fn get_or_try_init<E, F, Fut>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if let Some(v) = self.get(&key) {
+            return Ok(v);
+        }
+
+        // the gate of a key is reclaimed once nobody holds or waits for it
+        let _gate = self.gates.acquire(key.clone(), 1).await;
+        // initialized by the previous holder of the gate
+        if let Some(v) = self.get(&key) {
+            return Ok(v);
+        }
+
+        let v = f().await?;
+        self.values.lock().insert(key, v.clone());
+        Ok(v)
+    }
+}
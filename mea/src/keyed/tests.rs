@@ -0,0 +1,135 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio_test::assert_pending;
+use tokio_test::task::spawn;
+
+use crate::keyed::KeyedOnceCell;
+use crate::keyed::KeyedSemaphore;
+
+#[tokio::test]
+async fn same_key_is_limited() {
+    let limiter = Arc::new(KeyedSemaphore::new(2));
+    let running = Arc::new(std::sync::atomic::AtomicU32::new(0));
+
+    let handles = (0..16)
+        .map(|_| {
+            let limiter = limiter.clone();
+            let running = running.clone();
+            tokio::spawn(async move {
+                let _permit = limiter.acquire("key", 1).await;
+                let n = running.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                assert!(n < 2);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                running.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    assert!(limiter.is_empty());
+}
+
+#[test]
+fn different_keys_are_independent() {
+    let limiter = KeyedSemaphore::new(1);
+    let a = limiter.try_acquire(1, 1).unwrap();
+    let b = limiter.try_acquire(2, 1).unwrap();
+    assert!(limiter.try_acquire(1, 1).is_none());
+    assert_eq!(*a.key(), 1);
+    assert_eq!(*b.key(), 2);
+    assert_eq!(limiter.len(), 2);
+
+    drop(a);
+    assert_eq!(limiter.len(), 1);
+    drop(b);
+    assert!(limiter.is_empty());
+}
+
+#[test]
+fn idle_key_is_reclaimed() {
+    let limiter = KeyedSemaphore::new(1);
+    let held = limiter.try_acquire("key", 1).unwrap();
+
+    // a failed attempt does not keep the key alive
+    assert!(limiter.try_acquire("other", 2).is_none());
+    assert_eq!(limiter.len(), 1);
+
+    // a pending waiter keeps the key alive until it is cancelled
+    let mut waiter = spawn(limiter.acquire("key", 1));
+    assert_pending!(waiter.poll());
+    drop(held);
+    assert_eq!(limiter.len(), 1);
+    drop(waiter);
+    assert!(limiter.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn once_cell_initializes_each_key_once() {
+    let cell = Arc::new(KeyedOnceCell::new());
+    let calls = Arc::new(AtomicU32::new(0));
+    let running = Arc::new(AtomicU32::new(0));
+    let max_running = Arc::new(AtomicU32::new(0));
+
+    let handles = (0..16)
+        .map(|i| {
+            let cell = cell.clone();
+            let calls = calls.clone();
+            let running = running.clone();
+            let max_running = max_running.clone();
+            let key = i % 2;
+            tokio::spawn(async move {
+                cell.get_or_init(key, || async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    let n = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(n, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    key * 10
+                })
+                .await
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.await.unwrap(), (i as u32 % 2) * 10);
+    }
+    // once per key, and the two keys were initialized concurrently
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(max_running.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn once_cell_failed_init_lets_next_caller_try() {
+    let cell = KeyedOnceCell::new();
+    let mut first = spawn(cell.get_or_try_init("key", || async {
+        tokio::task::yield_now().await;
+        Err("failed")
+    }));
+    let mut second = spawn(cell.get_or_try_init("key", || async { Ok::<_, &str>(2) }));
+    assert_pending!(first.poll());
+    assert_pending!(second.poll());
+
+    assert_eq!(first.await, Err("failed"));
+    assert_eq!(second.await, Ok(2));
+    assert_eq!(cell.get(&"key"), Some(2));
+}
@@ -26,10 +26,15 @@
 //! * [`Barrier`]: A synchronization point where multiple tasks can wait until all participants
 //!   arrive
 //! * [`Condvar`]: A condition variable that allows tasks to wait for a notification
+//! * [`CountLatch`]: A reusable countdown latch that can be rearmed for another round
+//! * [`KeyedOnceCell`]: A map of values, each initialized at most once per key
+//! * [`KeyedSemaphore`]: A set of semaphores, one per key, created on demand
 //! * [`Latch`]: A single-use barrier that allows one or more tasks to wait until a signal is given
 //! * [`Mutex`]: A mutual exclusion primitive for protecting shared data
+//! * [`Rcu`]: A read-copy-update cell where readers never wait for writers
 //! * [`RwLock`]: A reader-writer lock that allows multiple readers or a single writer at a time
 //! * [`Semaphore`]: A synchronization primitive that controls access to a shared resource
+//! * [`Signal`]: A one-shot signal that permanently releases all waiters once notified
 //! * [`WaitGroup`]: A synchronization primitive that allows waiting for multiple tasks to complete
 //!
 //! ## Runtime Agnostic
@@ -44,24 +49,65 @@
 //! boundaries. This is essential for concurrent programming where data needs to be accessed from
 //! multiple threads.
 //!
+//! The only exception is [`LocalRwLock`], a lightweight lock meant for single-threaded executors.
+//!
+//! ## Feature Flags
+//!
+//! * `stream`: Implements [`futures_core::Stream`] for the streams in this crate, e.g., the permit
+//!   stream of a [`Semaphore`].
+//! * `tracing`: Emits [`tracing`] events when a [`Semaphore`] or [`RwLock`] starts waiting for
+//!   permits, is granted them, and releases them. Disabled by default, in which case the
+//!   instrumentation is compiled out entirely.
+//! * `unstable-internals`: Exposes the internal semaphore, and constructors of the primitives from
+//!   it, in the `internals` module. These APIs are exempt from semver.
+//!
+//! [`futures_core::Stream`]: https://docs.rs/futures-core/latest/futures_core/stream/trait.Stream.html
+//! [`tracing`]: https://docs.rs/tracing
 //! [`Barrier`]: barrier::Barrier
 //! [`Condvar`]: condvar::Condvar
+//! [`CountLatch`]: count_latch::CountLatch
+//! [`KeyedOnceCell`]: keyed::KeyedOnceCell
+//! [`KeyedSemaphore`]: keyed::KeyedSemaphore
 //! [`Latch`]: latch::Latch
 //! [`Mutex`]: mutex::Mutex
+//! [`LocalRwLock`]: rwlock::LocalRwLock
+//! [`Rcu`]: rcu::Rcu
 //! [`RwLock`]: rwlock::RwLock
 //! [`Semaphore`]: semaphore::Semaphore
+//! [`Signal`]: signal::Signal
 //! [`WaitGroup`]: waitgroup::WaitGroup
 
 pub(crate) mod internal;
 
 pub mod barrier;
 pub mod condvar;
+pub mod count_latch;
+pub mod keyed;
 pub mod latch;
 pub mod mutex;
+pub mod rcu;
 pub mod rwlock;
 pub mod semaphore;
+pub mod signal;
 pub mod waitgroup;
 
+#[cfg(feature = "unstable-internals")]
+pub mod internals {
+    //! Unstable access to the building blocks of the primitives in this crate.
+    //!
+    //! This module is only available with the `unstable-internals` feature. It is exempt from
+    //! semver: anything here may change or go away in any release.
+    //!
+    //! The primitives that can be built from these blocks, e.g., with
+    //! [`Mutex::from_semaphore`] and [`RwLock::from_semaphore`], validate them on construction,
+    //! and take the order of granting waiters from them.
+    //!
+    //! [`Mutex::from_semaphore`]: crate::mutex::Mutex::from_semaphore
+    //! [`RwLock::from_semaphore`]: crate::rwlock::RwLock::from_semaphore
+
+    pub use crate::internal::Semaphore;
+}
+
 #[cfg(test)]
 fn test_runtime() -> &'static tokio::runtime::Runtime {
     use std::sync::OnceLock;
@@ -75,13 +121,25 @@ fn test_runtime() -> &'static tokio::runtime::Runtime {
 mod tests {
     use crate::barrier::Barrier;
     use crate::condvar::Condvar;
+    use crate::count_latch::CountLatch;
+    use crate::keyed::KeyedOnceCell;
+    use crate::keyed::KeyedSemaphore;
     use crate::latch::Latch;
     use crate::mutex::Mutex;
     use crate::mutex::MutexGuard;
+    use crate::mutex::OwnedMappedMutexGuard;
+    use crate::mutex::OwnedMutexGuard;
+    use crate::rcu::Rcu;
+    use crate::rwlock::MappedRwLockReadGuard;
+    use crate::rwlock::MappedRwLockWriteGuard;
+    use crate::rwlock::OwnedRwLockReadGuard;
+    use crate::rwlock::OwnedRwLockWriteGuard;
     use crate::rwlock::RwLock;
     use crate::rwlock::RwLockReadGuard;
+    use crate::rwlock::RwLockUpgradableReadGuard;
     use crate::rwlock::RwLockWriteGuard;
     use crate::semaphore::Semaphore;
+    use crate::signal::Signal;
     use crate::waitgroup::WaitGroup;
 
     #[test]
@@ -89,14 +147,36 @@ mod tests {
         fn do_assert_send_and_sync<T: Send + Sync>() {}
         do_assert_send_and_sync::<Barrier>();
         do_assert_send_and_sync::<Condvar>();
+        do_assert_send_and_sync::<CountLatch>();
+        do_assert_send_and_sync::<KeyedOnceCell<String, i64>>();
+        do_assert_send_and_sync::<KeyedSemaphore<String>>();
         do_assert_send_and_sync::<Latch>();
         do_assert_send_and_sync::<Semaphore>();
+        do_assert_send_and_sync::<Signal>();
         do_assert_send_and_sync::<WaitGroup>();
         do_assert_send_and_sync::<Mutex<i64>>();
         do_assert_send_and_sync::<MutexGuard<'_, i64>>();
+        do_assert_send_and_sync::<OwnedMutexGuard<i64>>();
+        do_assert_send_and_sync::<OwnedMappedMutexGuard<(i64, i64), i64>>();
+        do_assert_send_and_sync::<Rcu<i64>>();
         do_assert_send_and_sync::<RwLock<i64>>();
         do_assert_send_and_sync::<RwLockReadGuard<'_, i64>>();
+        do_assert_send_and_sync::<MappedRwLockReadGuard<'_, i64>>();
+        do_assert_send_and_sync::<RwLockUpgradableReadGuard<'_, i64>>();
         do_assert_send_and_sync::<RwLockWriteGuard<'_, i64>>();
+        do_assert_send_and_sync::<MappedRwLockWriteGuard<'_, i64>>();
+        do_assert_send_and_sync::<OwnedRwLockWriteGuard<i64>>();
+    }
+
+    #[test]
+    fn assert_read_guard_sync_without_send() {
+        struct SyncNotSend(std::marker::PhantomData<*const ()>);
+        unsafe impl Sync for SyncNotSend {}
+
+        fn do_assert_sync<T: Sync>() {}
+        do_assert_sync::<RwLockReadGuard<'_, SyncNotSend>>();
+        do_assert_sync::<OwnedRwLockReadGuard<SyncNotSend>>();
+        do_assert_sync::<MappedRwLockReadGuard<'_, SyncNotSend>>();
     }
 
     #[test]
@@ -104,11 +184,16 @@ mod tests {
         fn do_assert_unpin<T: Unpin>() {}
         do_assert_unpin::<Barrier>();
         do_assert_unpin::<Condvar>();
+        do_assert_unpin::<CountLatch>();
+        do_assert_unpin::<KeyedOnceCell<String, i64>>();
+        do_assert_unpin::<KeyedSemaphore<String>>();
         do_assert_unpin::<Latch>();
         do_assert_unpin::<Semaphore>();
+        do_assert_unpin::<Signal>();
         do_assert_unpin::<WaitGroup>();
         do_assert_unpin::<Mutex<i64>>();
         do_assert_unpin::<MutexGuard<'_, i64>>();
+        do_assert_unpin::<Rcu<i64>>();
         do_assert_unpin::<RwLock<i64>>();
         do_assert_unpin::<RwLockReadGuard<'_, i64>>();
         do_assert_unpin::<RwLockWriteGuard<'_, i64>>();
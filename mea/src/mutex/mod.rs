@@ -22,6 +22,27 @@
 //! mutex can be created via [`new`] and the protected data can be accessed
 //! via the async [`lock`] method.
 //!
+//! # Fairness
+//!
+//! The mutex is fair: tasks waiting for the lock are granted it in the order they called
+//! [`lock`]. When the lock is released while other tasks are waiting, it is handed off directly
+//! to the first waiter, so a task calling [`try_lock`] or [`lock`] at that moment cannot barge
+//! ahead of the queue.
+//!
+//! A mutex created with [`new_unfair`] trades this guarantee for throughput. Releasing the lock
+//! leaves it free for whoever comes first, and only wakes the first waiter to race for it. A
+//! task that releases the lock and locks it again right away, or a task that is already running,
+//! keeps going instead of waiting for a parked waiter to be scheduled. Waiters can be overtaken
+//! any number of times, so the tail latency of `lock` grows under contention.
+//!
+//! The `mutex` benchmark (`cargo bench -p mea --bench mutex`) compares the two, with 2 to 16
+//! tasks whose critical sections yield once, on a Tokio runtime with 4 worker threads. Run on a
+//! single-core machine, the unfair mutex had from about the same to a third more throughput, and
+//! a median wait of tens of nanoseconds, against microseconds for the fair one. But its
+//! worst-case wait grew with the number of tasks to hundreds of milliseconds, while the fair
+//! mutex stayed within a few milliseconds. Prefer the fair mutex unless the lock is a measured
+//! bottleneck.
+//!
 //! # Examples
 //!
 //! ```
@@ -52,16 +73,22 @@
 //! ```
 //!
 //! [`new`]: Mutex::new
+//! [`new_unfair`]: Mutex::new_unfair
 //! [`lock`]: Mutex::lock
+//! [`try_lock`]: Mutex::try_lock
 
 use std::cell::UnsafeCell;
 use std::fmt;
+use std::mem::ManuallyDrop;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::sync::Arc;
 
 use crate::internal;
 
+#[cfg(test)]
+mod tests;
+
 /// An async mutex for protecting shared data.
 ///
 /// See the [module level documentation](self) for more.
@@ -80,6 +107,23 @@ impl<T> From<T> for Mutex<T> {
 }
 
 impl<T: Default> Default for Mutex<T> {
+    /// Creates an unlocked `Mutex` around `T::default()`.
+    ///
+    /// This allows deriving `Default` for types that contain a mutex.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::mutex::Mutex;
+    ///
+    /// #[derive(Default)]
+    /// struct Counter {
+    ///     hits: Mutex<u64>,
+    /// }
+    ///
+    /// let counter = Counter::default();
+    /// assert_eq!(*counter.hits.try_lock().unwrap(), 0);
+    /// ```
     fn default() -> Self {
         Self::new(T::default())
     }
@@ -112,6 +156,58 @@ impl<T> Mutex<T> {
         Self { s, c }
     }
 
+    /// Creates a new unfair mutex in an unlocked state ready for use.
+    ///
+    /// Tasks calling [`lock`] or [`try_lock`] may take the lock ahead of the tasks already
+    /// waiting for it. See the [module level documentation](self#fairness) for the tradeoff.
+    ///
+    /// [`lock`]: Mutex::lock
+    /// [`try_lock`]: Mutex::try_lock
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::mutex::Mutex;
+    ///
+    /// let mutex = Mutex::new_unfair(5);
+    /// assert_eq!(*mutex.try_lock().unwrap(), 5);
+    /// ```
+    pub fn new_unfair(t: T) -> Self {
+        let s = internal::Semaphore::barging(1);
+        let c = UnsafeCell::new(t);
+        Self { s, c }
+    }
+
+    /// Creates a new mutex from a pre-configured internal semaphore.
+    ///
+    /// The semaphore decides the order in which waiting tasks get the lock, e.g., a semaphore
+    /// created with [`Semaphore::with_order`] can grant the lock to the most recent waiter first.
+    /// This API is unstable, see the [`internals`](crate::internals) module.
+    ///
+    /// [`Semaphore::with_order`]: crate::internals::Semaphore::with_order
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` does not have exactly one available permit, or if it is closed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::internals::Semaphore;
+    /// use mea::mutex::Mutex;
+    ///
+    /// // a mutex that grants the lock to the most recent waiter first
+    /// let mutex = Mutex::from_semaphore(Semaphore::with_order(1, true), 5);
+    /// assert_eq!(*mutex.try_lock().unwrap(), 5);
+    /// ```
+    #[cfg(feature = "unstable-internals")]
+    pub fn from_semaphore(s: internal::Semaphore, t: T) -> Self {
+        assert_eq!(s.available_permits(), 1, "a mutex needs exactly one permit");
+        assert!(!s.is_closed(), "a mutex cannot be closed");
+        let c = UnsafeCell::new(t);
+        Self { s, c }
+    }
+
     /// Consumes the mutex, returning the underlying data.
     ///
     /// # Examples
@@ -138,8 +234,9 @@ impl<T: ?Sized> Mutex<T> {
     ///
     /// # Cancel safety
     ///
-    /// This method uses a queue to fairly distribute locks in the order they were requested.
-    /// Cancelling a call to `lock` makes you lose your place in the queue.
+    /// This method uses a queue to fairly distribute locks in the order they were requested,
+    /// unless the mutex is [unfair](Mutex::new_unfair). Cancelling a call to `lock` makes you
+    /// lose your place in the queue.
     ///
     /// # Examples
     ///
@@ -155,7 +252,7 @@ impl<T: ?Sized> Mutex<T> {
     /// # }
     /// ```
     pub async fn lock(&self) -> MutexGuard<'_, T> {
-        self.s.acquire(1).await;
+        self.s.acquire(1).await.expect("mutex is never closed");
         MutexGuard { lock: self }
     }
 
@@ -181,6 +278,26 @@ impl<T: ?Sized> Mutex<T> {
         }
     }
 
+    /// Attempts to acquire the lock, retrying up to `spins` times before giving up.
+    ///
+    /// Unlike [`lock`], this method never enqueues the caller. It is meant for very short
+    /// critical sections, where the holder is likely to release the lock within a brief spin.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::mutex::Mutex;
+    ///
+    /// let mutex = Mutex::new(1);
+    /// let guard = mutex.try_lock_spin(16).unwrap();
+    /// assert!(mutex.try_lock_spin(16).is_none());
+    /// ```
+    ///
+    /// [`lock`]: Mutex::lock
+    pub fn try_lock_spin(&self, spins: u32) -> Option<MutexGuard<'_, T>> {
+        internal::spin_try(spins, || self.try_lock())
+    }
+
     /// Locks this mutex, causing the current task to yield until the lock has been acquired. When
     /// the lock has been acquired, this returns an [`OwnedMutexGuard`].
     ///
@@ -214,7 +331,7 @@ impl<T: ?Sized> Mutex<T> {
     /// # }
     /// ```
     pub async fn lock_owned(self: Arc<Self>) -> OwnedMutexGuard<T> {
-        self.s.acquire(1).await;
+        self.s.acquire(1).await.expect("mutex is never closed");
         OwnedMutexGuard { lock: self }
     }
 
@@ -337,6 +454,53 @@ pub(crate) fn owned_guard_lock<T: ?Sized>(guard: &OwnedMutexGuard<T>) -> Arc<Mut
 
 unsafe impl<T: ?Sized + Send + Sync> Sync for OwnedMutexGuard<T> {}
 
+impl<T: ?Sized> OwnedMutexGuard<T> {
+    /// Makes a new [`OwnedMappedMutexGuard`] for a component of the locked data.
+    ///
+    /// This operation cannot fail as the [`OwnedMutexGuard`] passed in already locked the mutex.
+    ///
+    /// This is an associated function that needs to be used as `OwnedMutexGuard::map(...)`. A
+    /// method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::mutex::Mutex;
+    /// use mea::mutex::OwnedMutexGuard;
+    ///
+    /// #[derive(Debug)]
+    /// struct Foo {
+    ///     count: u32,
+    ///     name: String,
+    /// }
+    ///
+    /// let foo = Arc::new(Mutex::new(Foo {
+    ///     count: 1,
+    ///     name: "foo".to_string(),
+    /// }));
+    ///
+    /// let mut count = OwnedMutexGuard::map(foo.clone().lock_owned().await, |f| &mut f.count);
+    /// tokio::spawn(async move { *count += 1 }).await.unwrap();
+    /// assert_eq!(foo.lock().await.count, 2);
+    /// # }
+    /// ```
+    pub fn map<U, F>(this: Self, f: F) -> OwnedMappedMutexGuard<T, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let this = ManuallyDrop::new(this);
+        // SAFETY: the guard is not dropped, so the lock is moved into the mapped guard.
+        let lock = unsafe { std::ptr::read(&this.lock) };
+        let d = f(unsafe { &mut *lock.c.get() }) as *mut U;
+        OwnedMappedMutexGuard { d, lock }
+    }
+}
+
 impl<T: ?Sized> Drop for OwnedMutexGuard<T> {
     fn drop(&mut self) {
         self.lock.s.release(1);
@@ -367,3 +531,47 @@ impl<T: ?Sized> DerefMut for OwnedMutexGuard<T> {
         unsafe { &mut *self.lock.c.get() }
     }
 }
+
+/// An owned handle to a held `Mutex` that has had a function applied to it via
+/// [`OwnedMutexGuard::map`].
+///
+/// This can be used to hold a subfield of the protected data in a spawned task.
+#[must_use = "if unused the Mutex will immediately unlock"]
+pub struct OwnedMappedMutexGuard<T: ?Sized, U: ?Sized = T> {
+    d: *mut U,
+    lock: Arc<Mutex<T>>,
+}
+
+unsafe impl<T: ?Sized + Send, U: ?Sized + Send> Send for OwnedMappedMutexGuard<T, U> {}
+unsafe impl<T: ?Sized + Send, U: ?Sized + Send + Sync> Sync for OwnedMappedMutexGuard<T, U> {}
+
+impl<T: ?Sized, U: ?Sized> Drop for OwnedMappedMutexGuard<T, U> {
+    fn drop(&mut self) {
+        self.lock.s.release(1);
+    }
+}
+
+impl<T: ?Sized, U: ?Sized + fmt::Debug> fmt::Debug for OwnedMappedMutexGuard<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized, U: ?Sized + fmt::Display> fmt::Display for OwnedMappedMutexGuard<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Deref for OwnedMappedMutexGuard<T, U> {
+    type Target = U;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.d }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> DerefMut for OwnedMappedMutexGuard<T, U> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.d }
+    }
+}
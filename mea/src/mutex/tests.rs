@@ -0,0 +1,145 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use tokio_test::assert_pending;
+use tokio_test::assert_ready;
+use tokio_test::task::spawn;
+
+use crate::mutex::Mutex;
+use crate::mutex::OwnedMutexGuard;
+
+#[test]
+fn fifo_handoff() {
+    let mutex = Mutex::new(Vec::new());
+    let guard = mutex.try_lock().unwrap();
+
+    let mut waiters = (0..5)
+        .map(|i| {
+            let mutex = &mutex;
+            spawn(async move { mutex.lock().await.push(i) })
+        })
+        .collect::<Vec<_>>();
+    for w in waiters.iter_mut() {
+        assert_pending!(w.poll());
+    }
+
+    drop(guard);
+    for i in 0..5 {
+        // the lock is handed off to the first waiter, so it cannot be barged
+        assert!(mutex.try_lock().is_none());
+        assert!(waiters[i].is_woken());
+        for w in waiters[i + 1..].iter_mut() {
+            assert!(!w.is_woken());
+            assert_pending!(w.poll());
+        }
+        assert_ready!(waiters[i].poll());
+    }
+
+    assert_eq!(*mutex.try_lock().unwrap(), vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn unfair_mutex_can_be_barged() {
+    let mutex = Mutex::new_unfair(Vec::new());
+    let guard = mutex.try_lock().unwrap();
+
+    let mut waiters = (0..3)
+        .map(|i| {
+            let mutex = &mutex;
+            spawn(async move { mutex.lock().await.push(i) })
+        })
+        .collect::<Vec<_>>();
+    for w in waiters.iter_mut() {
+        assert_pending!(w.poll());
+    }
+
+    // only the first waiter is woken, and the lock is free for anyone to take meanwhile
+    drop(guard);
+    assert!(waiters[0].is_woken());
+    assert!(!waiters[1].is_woken());
+    let guard = mutex.try_lock().unwrap();
+    assert_pending!(waiters[0].poll());
+
+    // a woken waiter that goes away passes the wakeup on
+    drop(guard);
+    assert!(waiters[0].is_woken());
+    drop(waiters.remove(0));
+    assert!(waiters[0].is_woken());
+
+    let mut barger = spawn(async { mutex.lock().await.push(3) });
+    assert_ready!(barger.poll());
+    for w in waiters.iter_mut() {
+        assert_ready!(w.poll());
+    }
+    assert_eq!(*mutex.try_lock().unwrap(), vec![3, 1, 2]);
+}
+
+#[tokio::test]
+async fn owned_guard_held_across_await_in_task() {
+    let mutex = Arc::new(Mutex::new((0, String::new())));
+
+    let guard = mutex.clone().lock_owned().await;
+    let mut name = OwnedMutexGuard::map(guard, |(_, name)| name);
+    let handle = tokio::spawn(async move {
+        tokio::task::yield_now().await;
+        name.push_str("mea");
+    });
+    assert!(mutex.try_lock().is_none());
+
+    handle.await.unwrap();
+    assert_eq!(mutex.lock().await.1, "mea");
+}
+
+#[test]
+fn try_lock_spin_succeeds_when_released_within_budget() {
+    let mutex = Arc::new(Mutex::new(0));
+    let guard = mutex.clone().try_lock_owned().unwrap();
+    assert!(mutex.try_lock_spin(0).is_none());
+
+    let holder = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        drop(guard);
+    });
+    *mutex.try_lock_spin(u32::MAX).unwrap() += 1;
+    holder.join().unwrap();
+    assert_eq!(*mutex.try_lock().unwrap(), 1);
+}
+
+#[tokio::test]
+#[cfg(feature = "unstable-internals")]
+async fn mutex_from_semaphore() {
+    let mutex = Mutex::from_semaphore(crate::internals::Semaphore::with_order(1, true), 0);
+    let guard = mutex.lock().await;
+    let mut first = spawn(async { *mutex.lock().await += 1 });
+    let mut second = spawn(async { *mutex.lock().await *= 10 });
+    assert_pending!(first.poll());
+    assert_pending!(second.poll());
+
+    // the order of the semaphore is kept, so the most recent waiter goes first
+    drop(guard);
+    assert!(second.is_woken());
+    assert!(!first.is_woken());
+    assert_ready!(second.poll());
+    assert_ready!(first.poll());
+    assert_eq!(*mutex.lock().await, 1);
+}
+
+#[test]
+#[cfg(feature = "unstable-internals")]
+#[should_panic(expected = "exactly one permit")]
+fn mutex_from_semaphore_rejects_extra_permits() {
+    Mutex::from_semaphore(crate::internals::Semaphore::new(2), 0);
+}
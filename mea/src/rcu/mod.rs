@@ -0,0 +1,179 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A read-copy-update cell where readers never wait for writers.
+//!
+//! An [`Rcu`] holds the current version of a value behind an [`Arc`]. Readers take a snapshot of
+//! the current version with [`read()`], which never locks and never waits for a writer; a
+//! snapshot stays valid for as long as it is held, even after newer versions are published.
+//! Writers derive a new version from the current one with [`update()`]. Updates are serialized
+//! by an async [`Mutex`], and each new version is published atomically, so readers always
+//! observe a complete version.
+//!
+//! This suits extremely read-heavy data like configuration, where updates are rare and readers
+//! must not be slowed down by them.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() {
+//! use mea::rcu::Rcu;
+//!
+//! let config = Rcu::new(vec!["a"]);
+//! let snapshot = config.read();
+//!
+//! config.update(|v| [v.as_slice(), &["b"]].concat()).await;
+//! assert_eq!(*config.read(), vec!["a", "b"]);
+//!
+//! // the old snapshot is still valid
+//! assert_eq!(*snapshot, vec!["a"]);
+//! # }
+//! ```
+//!
+//! [`read()`]: Rcu::read
+//! [`update()`]: Rcu::update
+//! [`Mutex`]: crate::mutex::Mutex
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::mutex::Mutex;
+
+#[cfg(test)]
+mod tests;
+
+/// A read-copy-update cell where readers never wait for writers.
+///
+/// See the [module level documentation](self) for more.
+pub struct Rcu<T> {
+    /// The index of the slot holding the current version.
+    current: AtomicUsize,
+    /// The number of readers cloning the version out of each slot.
+    readers: [AtomicUsize; 2],
+    /// The current and the previous versions. A writer only replaces the slot that is not
+    /// current, once no reader is cloning out of it.
+    slots: [UnsafeCell<Arc<T>>; 2],
+    /// Serializes the writers.
+    writer: Mutex<()>,
+}
+
+unsafe impl<T: Send + Sync> Send for Rcu<T> {}
+unsafe impl<T: Send + Sync> Sync for Rcu<T> {}
+
+impl<T: Default> Default for Rcu<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for Rcu<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Rcu<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rcu").field("data", &self.read()).finish()
+    }
+}
+
+impl<T> Rcu<T> {
+    /// Creates a new RCU cell holding `value` as its first version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rcu::Rcu;
+    ///
+    /// let rcu = Rcu::new(5);
+    /// ```
+    pub fn new(value: T) -> Self {
+        let value = Arc::new(value);
+        Self {
+            current: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            slots: [UnsafeCell::new(value.clone()), UnsafeCell::new(value)],
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns a snapshot of the current version.
+    ///
+    /// This method never locks and never waits for a writer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rcu::Rcu;
+    ///
+    /// let rcu = Rcu::new(5);
+    /// assert_eq!(*rcu.read(), 5);
+    /// ```
+    pub fn read(&self) -> Arc<T> {
+        loop {
+            let idx = self.current.load(Ordering::SeqCst);
+            self.readers[idx].fetch_add(1, Ordering::SeqCst);
+            // If the slot is still current after announcing ourselves, a writer won't replace it
+            // until we are done. Otherwise, a writer may be replacing it right now, so retry.
+            if self.current.load(Ordering::SeqCst) == idx {
+                let version = unsafe { (*self.slots[idx].get()).clone() };
+                self.readers[idx].fetch_sub(1, Ordering::SeqCst);
+                return version;
+            }
+            self.readers[idx].fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Publishes a new version derived from the current one by `f`.
+    ///
+    /// Updates are serialized: this method waits for any other update to complete first. Readers
+    /// are never blocked, and observe either the previous or the new version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rcu::Rcu;
+    ///
+    /// let rcu = Rcu::new(1);
+    /// rcu.update(|v| v + 1).await;
+    /// assert_eq!(*rcu.read(), 2);
+    /// # }
+    /// ```
+    pub async fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&T) -> T,
+    {
+        let _writer = self.writer.lock().await;
+
+        let current = self.current.load(Ordering::SeqCst);
+        let next = 1 - current;
+        let version = Arc::new(f(&self.read()));
+
+        // Readers only announce themselves on the non-current slot if they raced with the
+        // previous update, and leave right away. Wait until the last of them has left.
+        while self.readers[next].load(Ordering::SeqCst) != 0 {
+            std::hint::spin_loop();
+        }
+        // Retire the previous version; readers that still hold it keep their own reference.
+        unsafe { *self.slots[next].get() = version };
+        self.current.store(next, Ordering::SeqCst);
+    }
+}
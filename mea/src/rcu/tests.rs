@@ -0,0 +1,71 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::rcu::Rcu;
+
+#[test]
+fn readers_observe_valid_versions() {
+    // every version is a vector of identical numbers, so a torn read would be detected
+    let rcu = Arc::new(Rcu::new(vec![0; 16]));
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let rcu = rcu.clone();
+            std::thread::spawn(move || {
+                let mut last = 0;
+                loop {
+                    let version = rcu.read();
+                    assert!(version.iter().all(|&n| n == version[0]));
+                    assert!(version[0] >= last, "versions go backwards");
+                    last = version[0];
+                    if last == 100 {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+
+    pollster::block_on(async {
+        for _ in 0..100 {
+            rcu.update(|v| vec![v[0] + 1; 16]).await;
+            std::thread::yield_now();
+        }
+    });
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    assert_eq!(*rcu.read(), vec![100; 16]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_updates_are_serialized() {
+    let rcu = Arc::new(Rcu::new(0));
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let rcu = rcu.clone();
+            tokio::spawn(async move {
+                for _ in 0..100 {
+                    rcu.update(|v| v + 1).await;
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    assert_eq!(*rcu.read(), 800);
+}
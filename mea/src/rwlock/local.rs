@@ -0,0 +1,330 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+/// A reader-writer lock for a single thread, with borrows checked at runtime.
+///
+/// `LocalRwLock` is a lightweight alternative to [`RwLock`] for single-threaded executors, where
+/// a value needs to be borrowed across `.await` points but never shared across threads. Instead
+/// of the semaphore machinery of [`RwLock`], it keeps a [`Cell`]-based borrow counter, so taking
+/// and releasing a guard costs a couple of non-atomic operations.
+///
+/// Since there is no other thread to make progress while a task waits, a conflicting borrow can
+/// never be resolved by waiting. Like [`RefCell`](std::cell::RefCell), [`read`] and [`write`]
+/// panic on a conflicting borrow, and [`try_read`] and [`try_write`] return `None` instead.
+///
+/// `LocalRwLock` is neither [`Send`] nor [`Sync`], regardless of `T`:
+///
+/// ```compile_fail
+/// use mea::rwlock::LocalRwLock;
+///
+/// fn assert_send<T: Send>(_: T) {}
+/// assert_send(LocalRwLock::new(1));
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use mea::rwlock::LocalRwLock;
+///
+/// let lock = LocalRwLock::new(5);
+///
+/// // many reader locks can be held at once
+/// {
+///     let r1 = lock.read();
+///     let r2 = lock.read();
+///     assert_eq!(*r1 + *r2, 10);
+/// } // read locks are dropped at this point
+///
+/// // only one write lock may be held, however
+/// {
+///     let mut w = lock.write();
+///     *w += 1;
+///     assert!(lock.try_read().is_none());
+/// } // write lock is dropped here
+///
+/// assert_eq!(*lock.read(), 6);
+/// ```
+///
+/// [`RwLock`]: crate::rwlock::RwLock
+/// [`read`]: LocalRwLock::read
+/// [`write`]: LocalRwLock::write
+/// [`try_read`]: LocalRwLock::try_read
+/// [`try_write`]: LocalRwLock::try_write
+pub struct LocalRwLock<T: ?Sized> {
+    /// The number of active readers, or `WRITING` if a writer holds the lock.
+    borrow: Cell<usize>,
+    /// Opts out of `Send` and `Sync`.
+    _local: PhantomData<*mut ()>,
+    /// The inner data.
+    c: UnsafeCell<T>,
+}
+
+const WRITING: usize = usize::MAX;
+
+impl<T> From<T> for LocalRwLock<T> {
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: Default> Default for LocalRwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for LocalRwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("LocalRwLock");
+        match self.try_read() {
+            Some(inner) => d.field("data", &&*inner),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+impl<T> LocalRwLock<T> {
+    /// Creates a new local reader-writer lock in an unlocked state ready for use.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::LocalRwLock;
+    ///
+    /// let lock = LocalRwLock::new(5);
+    /// ```
+    pub const fn new(t: T) -> LocalRwLock<T> {
+        LocalRwLock {
+            borrow: Cell::new(0),
+            _local: PhantomData,
+            c: UnsafeCell::new(t),
+        }
+    }
+
+    /// Consumes the lock, returning the underlying data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::LocalRwLock;
+    ///
+    /// let lock = LocalRwLock::new(1);
+    /// assert_eq!(lock.into_inner(), 1);
+    /// ```
+    pub fn into_inner(self) -> T {
+        self.c.into_inner()
+    }
+}
+
+impl<T: ?Sized> LocalRwLock<T> {
+    /// Locks this `LocalRwLock` with shared read access.
+    ///
+    /// Returns an RAII guard which will drop this read access when dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lock is currently held with write access. For a non-panicking variant, use
+    /// [`try_read`](LocalRwLock::try_read).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::LocalRwLock;
+    ///
+    /// let lock = LocalRwLock::new(1);
+    /// let r1 = lock.read();
+    /// let r2 = lock.read();
+    /// assert_eq!(*r1, *r2);
+    /// ```
+    #[track_caller]
+    pub fn read(&self) -> LocalRwLockReadGuard<'_, T> {
+        match self.try_read() {
+            Some(guard) => guard,
+            None => panic!("LocalRwLock is already locked for writing"),
+        }
+    }
+
+    /// Attempts to lock this `LocalRwLock` with shared read access.
+    ///
+    /// Returns `None` if the lock is currently held with write access.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::LocalRwLock;
+    ///
+    /// let lock = LocalRwLock::new(1);
+    /// let w = lock.write();
+    /// assert!(lock.try_read().is_none());
+    /// drop(w);
+    /// assert!(lock.try_read().is_some());
+    /// ```
+    pub fn try_read(&self) -> Option<LocalRwLockReadGuard<'_, T>> {
+        let borrow = self.borrow.get();
+        if borrow >= WRITING - 1 {
+            // either locked for writing, or the reader count would collide with `WRITING`
+            return None;
+        }
+        self.borrow.set(borrow + 1);
+        Some(LocalRwLockReadGuard { lock: self })
+    }
+
+    /// Locks this `LocalRwLock` with exclusive write access.
+    ///
+    /// Returns an RAII guard which will drop the write access when dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lock is currently held with read or write access. For a non-panicking
+    /// variant, use [`try_write`](LocalRwLock::try_write).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::LocalRwLock;
+    ///
+    /// let lock = LocalRwLock::new(1);
+    /// *lock.write() = 2;
+    /// assert_eq!(*lock.read(), 2);
+    /// ```
+    #[track_caller]
+    pub fn write(&self) -> LocalRwLockWriteGuard<'_, T> {
+        match self.try_write() {
+            Some(guard) => guard,
+            None => panic!("LocalRwLock is already locked"),
+        }
+    }
+
+    /// Attempts to lock this `LocalRwLock` with exclusive write access.
+    ///
+    /// Returns `None` if the lock is currently held with read or write access.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::LocalRwLock;
+    ///
+    /// let lock = LocalRwLock::new(1);
+    /// let r = lock.read();
+    /// assert!(lock.try_write().is_none());
+    /// drop(r);
+    /// assert!(lock.try_write().is_some());
+    /// ```
+    pub fn try_write(&self) -> Option<LocalRwLockWriteGuard<'_, T>> {
+        if self.borrow.get() != 0 {
+            return None;
+        }
+        self.borrow.set(WRITING);
+        Some(LocalRwLockWriteGuard { lock: self })
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `LocalRwLock` mutably, no actual locking needs to take place:
+    /// the mutable borrow statically guarantees no locks exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::LocalRwLock;
+    ///
+    /// let mut lock = LocalRwLock::new(1);
+    /// *lock.get_mut() = 2;
+    /// assert_eq!(*lock.read(), 2);
+    /// ```
+    pub fn get_mut(&mut self) -> &mut T {
+        self.c.get_mut()
+    }
+}
+
+/// RAII structure used to release the shared read access of a [`LocalRwLock`] when dropped.
+///
+/// This structure is created by the [`LocalRwLock::read`] and [`LocalRwLock::try_read`] methods.
+#[must_use = "if unused the LocalRwLock will immediately unlock"]
+pub struct LocalRwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a LocalRwLock<T>,
+}
+
+impl<T: ?Sized> Drop for LocalRwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.borrow.set(self.lock.borrow.get() - 1);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for LocalRwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for LocalRwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Deref for LocalRwLockReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.c.get() }
+    }
+}
+
+/// RAII structure used to release the exclusive write access of a [`LocalRwLock`] when dropped.
+///
+/// This structure is created by the [`LocalRwLock::write`] and [`LocalRwLock::try_write`]
+/// methods.
+#[must_use = "if unused the LocalRwLock will immediately unlock"]
+pub struct LocalRwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a LocalRwLock<T>,
+}
+
+impl<T: ?Sized> Drop for LocalRwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.borrow.set(0);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for LocalRwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for LocalRwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Deref for LocalRwLockWriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.c.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for LocalRwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.c.get() }
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Model checked tests of the public reader-writer lock API, see `internal::sync`.
+//!
+//! These tests are not part of the default test run. Run them with:
+//!
+//! ```shell
+//! LOOM_MAX_PREEMPTIONS=2 RUSTFLAGS="--cfg loom" cargo test -p mea --release --lib loom_tests
+//! ```
+
+use loom::cell::UnsafeCell;
+use loom::future::block_on;
+use loom::sync::Arc;
+use loom::thread;
+
+use crate::rwlock::RwLock;
+
+#[test]
+fn write_excludes_readers() {
+    loom::model(|| {
+        // the lock protects a loom cell, so that loom checks the access is well synchronized
+        let lock = Arc::new(RwLock::new(()));
+        let cell = Arc::new(UnsafeCell::new(0));
+
+        let writer = {
+            let lock = lock.clone();
+            let cell = cell.clone();
+            thread::spawn(move || {
+                block_on(async {
+                    let _w = lock.write().await;
+                    cell.with_mut(|n| unsafe { *n += 1 });
+                })
+            })
+        };
+        let reader = {
+            let lock = lock.clone();
+            let cell = cell.clone();
+            thread::spawn(move || {
+                block_on(async {
+                    let _r = lock.read().await;
+                    let n = cell.with(|n| unsafe { *n });
+                    assert!(n == 0 || n == 1);
+                })
+            })
+        };
+
+        block_on(async {
+            let _r = lock.read().await;
+            cell.with(|n| unsafe { *n });
+        });
+        writer.join().unwrap();
+        reader.join().unwrap();
+
+        assert_eq!(cell.with(|n| unsafe { *n }), 1);
+    });
+}
+
+#[test]
+fn writers_are_exclusive() {
+    loom::model(|| {
+        let lock = Arc::new(RwLock::with_max_readers((), 2));
+        let cell = Arc::new(UnsafeCell::new(0));
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let lock = lock.clone();
+                let cell = cell.clone();
+                thread::spawn(move || {
+                    block_on(async {
+                        let _w = lock.write().await;
+                        cell.with_mut(|n| unsafe { *n += 1 });
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(cell.with(|n| unsafe { *n }), 2);
+        assert!(lock.try_write().is_some());
+    });
+}
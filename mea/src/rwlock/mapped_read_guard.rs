@@ -0,0 +1,132 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::ops::Deref;
+
+use crate::internal;
+use crate::internal::Semaphore;
+
+/// RAII structure used to release the shared read access of a lock when dropped, which can point
+/// to a subfield of the protected data.
+///
+/// This structure is created by the [`RwLockReadGuard::map`] method. The target may be unsized,
+/// so a guard of `Box<dyn Trait>` can be mapped to `dyn Trait`.
+///
+/// [`RwLockReadGuard::map`]: crate::rwlock::RwLockReadGuard::map
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct MappedRwLockReadGuard<'a, T: ?Sized> {
+    pub(super) s: &'a Semaphore,
+    pub(super) d: *const T,
+}
+
+// A read guard only ever hands out `&T`, so it behaves like `&T` with regard to auto traits.
+unsafe impl<T: ?Sized + Sync> Send for MappedRwLockReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for MappedRwLockReadGuard<'_, T> {}
+
+impl<'a, T: ?Sized> MappedRwLockReadGuard<'a, T> {
+    /// Makes a new [`MappedRwLockReadGuard`] for a component of the locked data.
+    ///
+    /// This operation cannot fail as the guard passed in already locked the data.
+    ///
+    /// This is an associated function that needs to be used as `MappedRwLockReadGuard::map(...)`.
+    /// A method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::MappedRwLockReadGuard;
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockReadGuard;
+    ///
+    /// let lock = RwLock::new(((1, 2), 3));
+    /// let inner = RwLockReadGuard::map(lock.try_read().unwrap(), |v| &v.0);
+    /// let first = MappedRwLockReadGuard::map(inner, |v| &v.0);
+    /// assert_eq!(*first, 1);
+    /// ```
+    pub fn map<U, F>(this: Self, f: F) -> MappedRwLockReadGuard<'a, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&T) -> &U,
+    {
+        let d = f(&*this) as *const U;
+        let s = this.s;
+        std::mem::forget(this);
+        MappedRwLockReadGuard { s, d }
+    }
+
+    /// Attempts to make a new [`MappedRwLockReadGuard`] for a component of the locked data.
+    ///
+    /// If the closure returns `None`, the original guard is returned back, so the read access is
+    /// kept.
+    ///
+    /// This is an associated function that needs to be used as
+    /// `MappedRwLockReadGuard::filter_map(...)`. A method would interfere with methods of the same
+    /// name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::MappedRwLockReadGuard;
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockReadGuard;
+    ///
+    /// let lock = RwLock::new((vec![1, 2], 3));
+    /// let items = RwLockReadGuard::map(lock.try_read().unwrap(), |v| &v.0);
+    /// let items = MappedRwLockReadGuard::filter_map(items, |v| v.get(2)).unwrap_err();
+    /// let second = MappedRwLockReadGuard::filter_map(items, |v| v.get(1)).unwrap();
+    /// assert_eq!(*second, 2);
+    /// ```
+    pub fn filter_map<U, F>(this: Self, f: F) -> Result<MappedRwLockReadGuard<'a, U>, Self>
+    where
+        U: ?Sized,
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        match f(&*this) {
+            Some(d) => {
+                let d = d as *const U;
+                let s = this.s;
+                std::mem::forget(this);
+                Ok(MappedRwLockReadGuard { s, d })
+            }
+            None => Err(this),
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for MappedRwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.s.release(1);
+        internal::trace_release("rwlock", self.s, 1);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for MappedRwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for MappedRwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Deref for MappedRwLockReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.d }
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+use crate::internal::sync::atomic::AtomicBool;
+use crate::internal::sync::atomic::AtomicU64;
+use crate::internal::sync::atomic::AtomicUsize;
+use crate::internal::sync::Mutex;
+use crate::internal::Semaphore;
+use crate::internal::WakerSet;
+
+/// RAII structure used to release the exclusive write access of a lock when dropped, which can
+/// point to a subfield of the protected data.
+///
+/// This structure is created by the [`RwLockWriteGuard::map`] method.
+///
+/// [`RwLockWriteGuard::map`]: crate::rwlock::RwLockWriteGuard::map
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct MappedRwLockWriteGuard<'a, T: ?Sized> {
+    pub(super) permits_acquired: u32,
+    /// Whether tasks waiting for a write have been notified explicitly.
+    pub(super) notified: bool,
+    pub(super) s: &'a Semaphore,
+    pub(super) w: &'a Mutex<WakerSet>,
+    pub(super) waiting: &'a AtomicUsize,
+    pub(super) version: &'a AtomicU64,
+    pub(super) writing: &'a AtomicBool,
+    pub(super) d: *mut T,
+}
+
+// A write guard hands out `&mut T`, so it behaves like `&mut T` with regard to auto traits.
+unsafe impl<T: ?Sized + Send> Send for MappedRwLockWriteGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for MappedRwLockWriteGuard<'_, T> {}
+
+impl<'a, T: ?Sized> MappedRwLockWriteGuard<'a, T> {
+    /// Makes a new [`MappedRwLockWriteGuard`] for a component of the locked data.
+    ///
+    /// This operation cannot fail as the guard passed in already locked the data.
+    ///
+    /// This is an associated function that needs to be used as `MappedRwLockWriteGuard::map(...)`.
+    /// A method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::MappedRwLockWriteGuard;
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockWriteGuard;
+    ///
+    /// let lock = RwLock::new(((1, 2), 3));
+    /// let inner = RwLockWriteGuard::map(lock.try_write().unwrap(), |v| &mut v.0);
+    /// let mut first = MappedRwLockWriteGuard::map(inner, |v| &mut v.0);
+    /// *first = 0;
+    /// drop(first);
+    ///
+    /// assert_eq!(*lock.try_read().unwrap(), ((0, 2), 3));
+    /// ```
+    pub fn map<U, F>(mut this: Self, f: F) -> MappedRwLockWriteGuard<'a, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let d = f(&mut *this) as *mut U;
+        let permits_acquired = this.permits_acquired;
+        let notified = this.notified;
+        let (s, w, waiting) = (this.s, this.w, this.waiting);
+        let (version, writing) = (this.version, this.writing);
+        std::mem::forget(this);
+        MappedRwLockWriteGuard {
+            permits_acquired,
+            notified,
+            s,
+            w,
+            waiting,
+            version,
+            writing,
+            d,
+        }
+    }
+
+    /// Attempts to make a new [`MappedRwLockWriteGuard`] for a component of the locked data.
+    ///
+    /// If the closure returns `None`, the original guard is returned back, so the write access is
+    /// kept.
+    ///
+    /// This is an associated function that needs to be used as
+    /// `MappedRwLockWriteGuard::filter_map(...)`. A method would interfere with methods of the same
+    /// name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::MappedRwLockWriteGuard;
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockWriteGuard;
+    ///
+    /// let lock = RwLock::new((vec![1, 2], 3));
+    /// let items = RwLockWriteGuard::map(lock.try_write().unwrap(), |v| &mut v.0);
+    /// let items = MappedRwLockWriteGuard::filter_map(items, |v| v.get_mut(2)).unwrap_err();
+    /// let mut second = MappedRwLockWriteGuard::filter_map(items, |v| v.get_mut(1)).unwrap();
+    /// *second = 0;
+    /// drop(second);
+    ///
+    /// assert_eq!(*lock.try_read().unwrap(), (vec![1, 0], 3));
+    /// ```
+    pub fn filter_map<U, F>(mut this: Self, f: F) -> Result<MappedRwLockWriteGuard<'a, U>, Self>
+    where
+        U: ?Sized,
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        match f(&mut *this) {
+            Some(d) => {
+                let d = d as *mut U;
+                let permits_acquired = this.permits_acquired;
+                let notified = this.notified;
+                let (s, w, waiting) = (this.s, this.w, this.waiting);
+                let (version, writing) = (this.version, this.writing);
+                std::mem::forget(this);
+                Ok(MappedRwLockWriteGuard {
+                    permits_acquired,
+                    notified,
+                    s,
+                    w,
+                    waiting,
+                    version,
+                    writing,
+                    d,
+                })
+            }
+            None => Err(this),
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for MappedRwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        super::release_write(
+            self.s,
+            self.w,
+            self.waiting,
+            self.version,
+            self.writing,
+            self.permits_acquired,
+            self.notified,
+        );
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for MappedRwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for MappedRwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Deref for MappedRwLockWriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.d }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MappedRwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.d }
+    }
+}
@@ -67,17 +67,45 @@
 use std::cell::UnsafeCell;
 use std::fmt;
 
+use crate::internal;
+use crate::internal::sync::atomic::AtomicBool;
+use crate::internal::sync::atomic::AtomicU64;
+use crate::internal::sync::atomic::AtomicUsize;
+use crate::internal::sync::atomic::Ordering;
+use crate::internal::sync::Mutex;
 use crate::internal::Semaphore;
+use crate::internal::WakerSet;
 
+mod local;
+pub use local::LocalRwLock;
+pub use local::LocalRwLockReadGuard;
+pub use local::LocalRwLockWriteGuard;
+mod mapped_read_guard;
+pub use mapped_read_guard::MappedRwLockReadGuard;
+mod mapped_write_guard;
+pub use mapped_write_guard::MappedRwLockWriteGuard;
 mod owned_read_guard;
 pub use owned_read_guard::OwnedRwLockReadGuard;
 mod owned_write_guard;
 pub use owned_write_guard::OwnedRwLockWriteGuard;
 mod read_guard;
 pub use read_guard::RwLockReadGuard;
+mod upgradable_read_guard;
+pub use upgradable_read_guard::RwLockUpgradableReadGuard;
 mod write_guard;
 pub use write_guard::RwLockWriteGuard;
 
+#[cfg(all(test, loom))]
+mod loom_tests;
+#[cfg(test)]
+mod tests;
+
+/// The maximum number of readers of a lock created by [`RwLock::new`].
+///
+/// A writer acquires all the permits at once, so any number up to the semaphore's ceiling works.
+/// Half of the ceiling is large enough while staying clear of the edge.
+const DEFAULT_MAX_READERS: u32 = Semaphore::MAX_PERMITS >> 1;
+
 /// A reader-writer lock that allows multiple readers or a single writer at a time.
 ///
 /// See the [module level documentation](self) for more.
@@ -86,6 +114,17 @@ pub struct RwLock<T: ?Sized> {
     max_readers: u32,
     /// Semaphore to coordinate read and write access to T
     s: Semaphore,
+    /// The upgrade permit, held by the only upgradable reader, see [`RwLock::upgradable_read`].
+    /// Writers never take it.
+    u: Semaphore,
+    /// Tasks waiting for a writer to release the lock, see [`RwLock::wait_until_read`].
+    w: Mutex<WakerSet>,
+    /// The number of tasks registered in `w`, so that writers skip its lock when there are none.
+    waiting: AtomicUsize,
+    /// The number of writes, see [`RwLock::version`].
+    version: AtomicU64,
+    /// Whether a writer holds the lock, see [`RwLock::lock_state`].
+    writing: AtomicBool,
     /// The inner data.
     c: UnsafeCell<T>,
 }
@@ -100,6 +139,27 @@ impl<T> From<T> for RwLock<T> {
 }
 
 impl<T: Default> Default for RwLock<T> {
+    /// Creates an unlocked `RwLock` around `T::default()`, with the same maximum number of
+    /// readers as [`RwLock::new`].
+    ///
+    /// This allows deriving `Default` for types that contain a lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    ///
+    /// #[derive(Default)]
+    /// struct Config {
+    ///     name: String,
+    ///     payload: RwLock<Vec<u8>>,
+    /// }
+    ///
+    /// let config = Config::default();
+    /// assert!(config.name.is_empty());
+    /// assert!(config.payload.try_read().unwrap().is_empty());
+    /// assert_eq!(config.payload.max_readers(), RwLock::new(()).max_readers());
+    /// ```
     fn default() -> Self {
         Self::new(T::default())
     }
@@ -127,9 +187,7 @@ impl<T> RwLock<T> {
     /// let rwlock = RwLock::new(5);
     /// ```
     pub fn new(t: T) -> RwLock<T> {
-        // large enough while not touch the edge
-        let default_max_readers = u32::MAX >> 1;
-        RwLock::with_max_readers(t, default_max_readers)
+        RwLock::with_max_readers(t, DEFAULT_MAX_READERS)
     }
 
     /// Creates a new reader-writer lock in an unlocked state, and allows a maximum of
@@ -146,8 +204,94 @@ impl<T> RwLock<T> {
     /// ```
     pub fn with_max_readers(t: T, max_readers: u32) -> RwLock<T> {
         let s = Semaphore::new(max_readers);
+        let u = Semaphore::new(1);
+        let w = Mutex::new(WakerSet::new());
         let c = UnsafeCell::new(t);
-        RwLock { max_readers, c, s }
+        RwLock {
+            max_readers,
+            c,
+            s,
+            u,
+            w,
+            waiting: AtomicUsize::new(0),
+            version: AtomicU64::new(0),
+            writing: AtomicBool::new(false),
+        }
+    }
+
+    /// Creates a new reader-writer lock from a pre-configured internal semaphore, which allows a
+    /// maximum of `max_readers` concurrent readers.
+    ///
+    /// The semaphore decides the order in which waiting readers and writers get the lock, e.g.,
+    /// a semaphore created with [`Semaphore::barging`] lets new readers take the lock ahead of
+    /// the waiters, at the risk of starving writers. A closed semaphore gives a
+    /// [closed](RwLock::close) lock. This API is unstable, see the [`internals`](crate::internals)
+    /// module.
+    ///
+    /// [`Semaphore::barging`]: crate::internals::Semaphore::barging
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` does not have exactly `max_readers` available permits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::internals::Semaphore;
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let s = Semaphore::new(8);
+    /// s.close();
+    /// let lock = RwLock::from_semaphore(s, 5, 8);
+    /// assert!(lock.is_closed());
+    /// assert_eq!(lock.max_readers(), 8);
+    /// ```
+    #[cfg(feature = "unstable-internals")]
+    pub fn from_semaphore(s: Semaphore, t: T, max_readers: u32) -> RwLock<T> {
+        assert_eq!(
+            s.available_permits(),
+            max_readers,
+            "a reader-writer lock needs exactly `max_readers` permits"
+        );
+        let u = Semaphore::new(1);
+        if s.is_closed() {
+            u.close();
+        }
+        let w = Mutex::new(WakerSet::new());
+        let c = UnsafeCell::new(t);
+        RwLock {
+            max_readers,
+            c,
+            s,
+            u,
+            w,
+            waiting: AtomicUsize::new(0),
+            version: AtomicU64::new(0),
+            writing: AtomicBool::new(false),
+        }
+    }
+
+    /// Creates a new reader-writer lock in an unlocked state, and allows as many concurrent
+    /// readers as the hardware concurrency.
+    ///
+    /// The maximum number of readers is [`std::thread::available_parallelism`], but never less
+    /// than 4. If the available parallelism cannot be determined, 4 is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let rwlock = RwLock::with_parallelism(5);
+    /// ```
+    pub fn with_parallelism(t: T) -> RwLock<T> {
+        const MIN_READERS: u32 = 4;
+        let max_readers = std::thread::available_parallelism()
+            .map_or(MIN_READERS, |n| {
+                u32::try_from(n.get()).unwrap_or(DEFAULT_MAX_READERS)
+            })
+            .clamp(MIN_READERS, DEFAULT_MAX_READERS);
+        RwLock::with_max_readers(t, max_readers)
     }
 
     /// Consumes the lock, returning the underlying data.
@@ -164,6 +308,202 @@ impl<T> RwLock<T> {
     pub fn into_inner(self) -> T {
         self.c.into_inner()
     }
+
+    /// Replaces the protected value with `value` under the write lock, returning the old value.
+    ///
+    /// The write lock is released before this method returns, so dropping the old value never
+    /// happens while the lock is held.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// assert_eq!(lock.replace(2).await, 1);
+    /// assert_eq!(*lock.read().await, 2);
+    /// # }
+    /// ```
+    pub async fn replace(&self, value: T) -> T {
+        let mut guard = self.write().await;
+        std::mem::replace(&mut *guard, value)
+    }
+
+    /// Sets the protected value to `value` under the write lock.
+    ///
+    /// Like [`replace`], this acquires the full write lock, and the old value is dropped after the
+    /// lock is released.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// lock.set(2).await;
+    /// assert_eq!(*lock.read().await, 2);
+    /// # }
+    /// ```
+    ///
+    /// [`replace`]: RwLock::replace
+    pub async fn set(&self, value: T) {
+        drop(self.replace(value).await);
+    }
+
+    /// Attempts to replace the protected value with `value`, returning the old value.
+    ///
+    /// This acquires the full write lock like [`replace`]. If the lock couldn't be acquired
+    /// immediately, returns `value` back as the error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// assert_eq!(lock.try_replace(2), Ok(1));
+    ///
+    /// let guard = lock.try_read().unwrap();
+    /// assert_eq!(lock.try_replace(3), Err(3));
+    /// drop(guard);
+    /// assert_eq!(*lock.try_read().unwrap(), 2);
+    /// ```
+    ///
+    /// [`replace`]: RwLock::replace
+    pub fn try_replace(&self, value: T) -> Result<T, T> {
+        match self.try_write() {
+            Some(mut guard) => Ok(std::mem::replace(&mut *guard, value)),
+            None => Err(value),
+        }
+    }
+
+    /// Attempts to set the protected value to `value`.
+    ///
+    /// This acquires the full write lock like [`set`]. If the lock couldn't be acquired
+    /// immediately, returns `value` back as the error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let guard = lock.try_read().unwrap();
+    /// assert_eq!(lock.try_set(2), Err(2));
+    /// drop(guard);
+    ///
+    /// assert_eq!(lock.try_set(2), Ok(()));
+    /// assert_eq!(*lock.try_read().unwrap(), 2);
+    /// ```
+    ///
+    /// [`set`]: RwLock::set
+    pub fn try_set(&self, value: T) -> Result<(), T> {
+        self.try_replace(value).map(drop)
+    }
+
+    /// Takes the protected value under the write lock, leaving `T::default()` in its place.
+    ///
+    /// The write lock is released before this method returns, as with [`replace`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(vec![1, 2]);
+    /// assert_eq!(lock.take().await, vec![1, 2]);
+    /// assert!(lock.read().await.is_empty());
+    /// # }
+    /// ```
+    ///
+    /// [`replace`]: RwLock::replace
+    pub async fn take(&self) -> T
+    where
+        T: Default,
+    {
+        self.replace(T::default()).await
+    }
+
+    /// Updates the protected value with `f` optimistically, computing outside the lock.
+    ///
+    /// The value is cloned under a read lock along with its [version](RwLock::version), and `f`
+    /// computes the new value from the clone without holding any lock. The new value is then
+    /// stored under the write lock, but only if no other write has happened in the meantime.
+    /// Otherwise, the update starts over with the latest value, so no write is ever lost. A
+    /// discarded attempt does not count as a write.
+    ///
+    /// This keeps an expensive `f` from holding the write lock, at the cost of rerunning it on
+    /// conflicts. Returns the number of times `f` has been called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(vec![3, 1, 2]);
+    /// let calls = lock
+    ///     .optimistic_update(|v| {
+    ///         let mut v = v.clone();
+    ///         v.sort();
+    ///         v
+    ///     })
+    ///     .await;
+    /// assert_eq!(calls, 1);
+    /// assert_eq!(*lock.read().await, [1, 2, 3]);
+    /// # }
+    /// ```
+    pub async fn optimistic_update<F>(&self, mut f: F) -> usize
+    where
+        T: Clone,
+        F: FnMut(&T) -> T,
+    {
+        let mut calls = 0;
+        loop {
+            let (seen, snapshot) = {
+                let guard = self.read().await;
+                (RwLockReadGuard::version(&guard), T::clone(&guard))
+            };
+
+            let value = f(&snapshot);
+            calls += 1;
+
+            let mut guard = self.write().await;
+            if self.version() == seen {
+                *guard = value;
+                return calls;
+            }
+
+            // nothing is written, so release without advancing the version; otherwise, conflicting
+            // attempts would keep failing each other
+            let permits = guard.permits_acquired;
+            std::mem::forget(guard);
+            release_writer_permits(&self.s, &self.writing, permits);
+        }
+    }
 }
 
 impl<T: ?Sized> RwLock<T> {
@@ -184,4 +524,345 @@ impl<T: ?Sized> RwLock<T> {
     pub fn get_mut(&mut self) -> &mut T {
         self.c.get_mut()
     }
+
+    /// Closes this `RwLock`.
+    ///
+    /// All tasks waiting for the lock are woken up: [`read_checked`] and [`write_checked`] return
+    /// `Err(Closed)`, and so do subsequent calls. Guards that are already held remain valid until
+    /// dropped. The `try_*` methods return `None` once the lock is closed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::Closed;
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let r = lock.read().await;
+    ///
+    /// lock.close();
+    /// assert!(lock.is_closed());
+    /// assert_eq!(*r, 1);
+    /// assert_eq!(lock.write_checked().await.unwrap_err(), Closed);
+    /// # }
+    /// ```
+    ///
+    /// [`read_checked`]: RwLock::read_checked
+    /// [`write_checked`]: RwLock::write_checked
+    pub fn close(&self) {
+        self.s.close();
+        self.u.close();
+        self.w.lock().notify_all();
+    }
+
+    /// Returns `true` if this `RwLock` is [closed](RwLock::close).
+    pub fn is_closed(&self) -> bool {
+        self.s.is_closed()
+    }
+
+    /// Returns the maximum number of concurrent readers this lock allows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let rwlock = RwLock::with_max_readers(5, 1024);
+    /// assert_eq!(rwlock.max_readers(), 1024);
+    ///
+    /// let rwlock = RwLock::with_parallelism(5);
+    /// assert!(rwlock.max_readers() >= 4);
+    /// ```
+    pub fn max_readers(&self) -> u32 {
+        self.max_readers
+    }
+
+    /// Returns the number of writes to this lock so far.
+    ///
+    /// The version is incremented exactly once for every write guard, when it releases the lock.
+    /// Comparing it with a previously observed version tells whether the data may have changed
+    /// since. Use [`RwLockReadGuard::version`] to read the version consistently with the data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let seen = lock.version();
+    ///
+    /// *lock.write().await += 1;
+    /// assert_ne!(lock.version(), seen);
+    /// # }
+    /// ```
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// Returns whether the lock is currently free, held by readers, or held by a writer.
+    ///
+    /// The state is a snapshot meant for diagnostics, which may be outdated as soon as it is
+    /// returned. Permits already handed to a task that is still waiting for more are not counted
+    /// as held, and an upgradable reader counts as a reader. A writer that has been granted the
+    /// lock is reported as such once it resumes; until then, the lock looks fully read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::LockState;
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// assert_eq!(lock.lock_state(), LockState::Free);
+    ///
+    /// let r1 = lock.try_read().unwrap();
+    /// let r2 = lock.try_read().unwrap();
+    /// assert_eq!(lock.lock_state(), LockState::Read(2));
+    /// drop((r1, r2));
+    ///
+    /// let w = lock.try_write().unwrap();
+    /// assert_eq!(lock.lock_state(), LockState::Write);
+    /// ```
+    pub fn lock_state(&self) -> LockState {
+        if self.writing.load(Ordering::Acquire) {
+            return LockState::Write;
+        }
+        let unheld = self.s.available_permits().saturating_add(self.s.assigned());
+        match self.max_readers.saturating_sub(unheld) {
+            0 => LockState::Free,
+            readers => LockState::Read(readers),
+        }
+    }
+
+    /// Acquires the permits of a writer.
+    ///
+    /// Writers wait in the same queue as readers. An upgrading reader waits ahead of them, see
+    /// [`RwLockUpgradableReadGuard::upgrade`].
+    async fn acquire_write(&self) -> Result<(), Closed> {
+        self.s.acquire(self.max_readers).await.map_err(|_| Closed)?;
+        self.writing.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Attempts to acquire the permits of a writer, see [`RwLock::acquire_write`].
+    fn try_acquire_write(&self) -> bool {
+        let acquired = self.s.try_acquire(self.max_readers);
+        if acquired {
+            self.writing.store(true, Ordering::Release);
+        }
+        acquired
+    }
+
+    /// Releases the permits held by a writer.
+    ///
+    /// Unless the writer has notified the tasks waiting for a write explicitly, they are all
+    /// notified.
+    fn release_write(&self, permits: u32, notified: bool) {
+        release_write(
+            &self.s,
+            &self.w,
+            &self.waiting,
+            &self.version,
+            &self.writing,
+            permits,
+            notified,
+        );
+    }
+}
+
+/// Releases the permits held by a writer, see [`RwLock::release_write`].
+///
+/// Mapped guards no longer know the type of the locked data, so they release with the parts of
+/// the lock.
+fn release_write(
+    s: &Semaphore,
+    w: &Mutex<WakerSet>,
+    waiting: &AtomicUsize,
+    version: &AtomicU64,
+    writing: &AtomicBool,
+    permits: u32,
+    notified: bool,
+) {
+    // bump the version before any reader can observe the written data
+    version.fetch_add(1, Ordering::Release);
+    release_writer_permits(s, writing, permits);
+    if !notified && waiting.load(Ordering::SeqCst) > 0 {
+        w.lock().notify_all();
+    }
+}
+
+/// Releases the permits held by a writer without counting a write, see [`release_write`].
+fn release_writer_permits(s: &Semaphore, writing: &AtomicBool, permits: u32) {
+    writing.store(false, Ordering::Release);
+    s.release(permits);
+    internal::trace_release("rwlock", s, permits);
+}
+
+/// Releases the upgrade permit when dropped.
+struct UpgradeGate<'a>(&'a Semaphore);
+
+impl Drop for UpgradeGate<'_> {
+    fn drop(&mut self) {
+        self.0.release(1);
+    }
+}
+
+/// An error returned from [`RwLock::read_checked`] and [`RwLock::write_checked`] when the lock
+/// is [closed](RwLock::close).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("lock closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+/// The state of a [`RwLock`], returned from [`RwLock::lock_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockState {
+    /// No one holds the lock.
+    Free,
+    /// The lock is held by the given number of readers.
+    Read(u32),
+    /// The lock is held by a writer.
+    Write,
+}
+
+impl<T: Copy> RwLock<T> {
+    /// Returns a copy of the protected value, holding the read lock only while copying.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// assert_eq!(lock.load_copy().await, 1);
+    /// # }
+    /// ```
+    pub async fn load_copy(&self) -> T {
+        *self.read().await
+    }
+
+    /// Stores a new value, holding the write lock only while storing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// lock.store_copy(2).await;
+    /// assert_eq!(lock.load_copy().await, 2);
+    /// # }
+    /// ```
+    pub async fn store_copy(&self, value: T) {
+        *self.write().await = value;
+    }
+
+    /// Updates the protected value with `f` under a single write lock, and returns the previous
+    /// value.
+    ///
+    /// Since the read and the write happen under the same write lock, concurrent updates are
+    /// never lost.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// assert_eq!(lock.fetch_update(|n| n + 1).await, 1);
+    /// assert_eq!(lock.load_copy().await, 2);
+    /// # }
+    /// ```
+    pub async fn fetch_update<F>(&self, f: F) -> T
+    where
+        F: FnOnce(T) -> T,
+    {
+        let mut guard = self.write().await;
+        let prev = *guard;
+        *guard = f(prev);
+        prev
+    }
+}
+
+/// Locks all the given `RwLock`s with exclusive write access, causing the current task to yield
+/// until all the locks have been acquired.
+///
+/// The locks are acquired in a global order (by address) regardless of their order in `locks`,
+/// so that tasks locking overlapping sets of locks in different orders cannot deadlock each other.
+/// The returned guards are in the same order as `locks`.
+///
+/// # Panics
+///
+/// Panics if the same lock appears more than once in `locks`, or if any of them is
+/// [closed](RwLock::close).
+///
+/// # Cancel safety
+///
+/// This method uses a queue to fairly distribute locks in the order they were requested.
+/// Cancelling a call to `write_all` makes you lose your place in the queue, and releases all the
+/// locks acquired so far.
+///
+/// # Examples
+///
+/// ```
+/// # #[tokio::main]
+/// # async fn main() {
+/// use mea::rwlock::RwLock;
+///
+/// let a = RwLock::new(1);
+/// let b = RwLock::new(2);
+///
+/// let mut guards = mea::rwlock::write_all(&[&a, &b]).await;
+/// if let [a, b] = &mut guards[..] {
+///     std::mem::swap(&mut **a, &mut **b);
+/// }
+/// drop(guards);
+///
+/// assert_eq!(*a.read().await, 2);
+/// assert_eq!(*b.read().await, 1);
+/// # }
+/// ```
+pub async fn write_all<'a, T: ?Sized>(locks: &[&'a RwLock<T>]) -> Vec<RwLockWriteGuard<'a, T>> {
+    let addr = |i: usize| locks[i] as *const RwLock<T> as *const () as usize;
+
+    let mut order = (0..locks.len()).collect::<Vec<_>>();
+    order.sort_unstable_by_key(|&i| addr(i));
+    assert!(
+        order.windows(2).all(|w| addr(w[0]) != addr(w[1])),
+        "the same lock cannot be locked more than once"
+    );
+
+    let mut guards = locks.iter().map(|_| None).collect::<Vec<_>>();
+    for i in order {
+        guards[i] = Some(locks[i].write().await);
+    }
+    guards.into_iter().map(Option::unwrap).collect()
 }
@@ -16,6 +16,7 @@ use std::fmt;
 use std::ops::Deref;
 use std::sync::Arc;
 
+use crate::internal;
 use crate::rwlock::RwLock;
 
 impl<T: ?Sized> RwLock<T> {
@@ -40,7 +41,13 @@ impl<T: ?Sized> RwLock<T> {
     /// # Cancel safety
     ///
     /// This method uses a queue to fairly distribute locks in the order they were requested.
-    /// Cancelling a call to `read_owned` makes you lose your place in the queue.
+    /// Cancelling a call to `read_owned` makes you lose your place in the queue, and gives back any
+    /// permits already assigned to it. So giving up after a timeout by racing `read_owned` against
+    /// a timer of your runtime, e.g., with `tokio::time::timeout`, never leaks the lock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
     ///
     /// # Examples
     ///
@@ -67,7 +74,9 @@ impl<T: ?Sized> RwLock<T> {
     /// # }
     /// ```
     pub async fn read_owned(self: Arc<Self>) -> OwnedRwLockReadGuard<T> {
-        self.s.acquire(1).await;
+        if self.s.acquire(1).await.is_err() {
+            panic!("RwLock is closed");
+        }
         OwnedRwLockReadGuard { lock: self }
     }
 
@@ -108,18 +117,21 @@ impl<T: ?Sized> RwLock<T> {
 
 /// Owned RAII structure used to release the shared read access of a lock when dropped.
 ///
-/// This structure is created by the [`RwLock::read`] method.
+/// This structure is created by the [`RwLock::read_owned`] and [`RwLock::try_read_owned`] methods.
 #[must_use = "if unused the RwLock will immediately unlock"]
 pub struct OwnedRwLockReadGuard<T: ?Sized> {
     pub(super) lock: Arc<RwLock<T>>,
 }
 
-unsafe impl<T: ?Sized + Sync> Send for OwnedRwLockReadGuard<T> {}
-unsafe impl<T: ?Sized + Send + Sync> Sync for OwnedRwLockReadGuard<T> {}
+// Sending the guard may drop the last `Arc` on another thread, which drops `T` there; sharing the
+// guard only ever hands out `&T`.
+unsafe impl<T: ?Sized + Send + Sync> Send for OwnedRwLockReadGuard<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for OwnedRwLockReadGuard<T> {}
 
 impl<T: ?Sized> Drop for OwnedRwLockReadGuard<T> {
     fn drop(&mut self) {
         self.lock.s.release(1);
+        internal::trace_release("rwlock", &self.lock.s, 1);
     }
 }
 
@@ -36,7 +36,13 @@ impl<T: ?Sized> RwLock<T> {
     /// # Cancel safety
     ///
     /// This method uses a queue to fairly distribute locks in the order they were requested.
-    /// Cancelling a call to `write_owned` makes you lose your place in the queue.
+    /// Cancelling a call to `write_owned` makes you lose your place in the queue, and gives back
+    /// any permits already assigned to it. So giving up after a timeout by racing `write_owned`
+    /// against a timer of your runtime, e.g., with `tokio::time::timeout`, never leaks the lock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
     ///
     /// # Examples
     ///
@@ -53,9 +59,12 @@ impl<T: ?Sized> RwLock<T> {
     /// # }
     /// ```
     pub async fn write_owned(self: Arc<Self>) -> OwnedRwLockWriteGuard<T> {
-        self.s.acquire(self.max_readers).await;
+        if self.acquire_write().await.is_err() {
+            panic!("RwLock is closed");
+        }
         OwnedRwLockWriteGuard {
             permits_acquired: self.max_readers,
+            notified: false,
             lock: self,
         }
     }
@@ -87,9 +96,10 @@ impl<T: ?Sized> RwLock<T> {
     /// *v = 2;
     /// ```
     pub fn try_write_owned(self: Arc<Self>) -> Option<OwnedRwLockWriteGuard<T>> {
-        if self.s.try_acquire(self.max_readers) {
+        if self.try_acquire_write() {
             Some(OwnedRwLockWriteGuard {
                 permits_acquired: self.max_readers,
+                notified: false,
                 lock: self,
             })
         } else {
@@ -100,19 +110,48 @@ impl<T: ?Sized> RwLock<T> {
 
 /// Owned RAII structure used to release the exclusive write access of a lock when dropped.
 ///
-/// This structure is created by the [`RwLock::write`] method.
+/// This structure is created by the [`RwLock::write_owned`] and [`RwLock::try_write_owned`]
+/// methods.
 #[must_use = "if unused the RwLock will immediately unlock"]
 pub struct OwnedRwLockWriteGuard<T: ?Sized> {
     pub(super) permits_acquired: u32,
+    /// Whether tasks waiting for a write have been notified explicitly.
+    pub(super) notified: bool,
     pub(super) lock: Arc<RwLock<T>>,
 }
 
 unsafe impl<T: ?Sized + Send + Sync> Send for OwnedRwLockWriteGuard<T> {}
 unsafe impl<T: ?Sized + Send + Sync> Sync for OwnedRwLockWriteGuard<T> {}
 
+impl<T: ?Sized> OwnedRwLockWriteGuard<T> {
+    /// Notifies one task waiting in [`RwLock::wait_until_read`] to re-check its condition.
+    ///
+    /// The notified task re-checks its condition once this write access is released. By default,
+    /// all waiting tasks are notified when the write guard is dropped; once this method or
+    /// [`notify_all_waiters`](OwnedRwLockWriteGuard::notify_all_waiters) is called, only the
+    /// explicitly notified tasks are.
+    ///
+    /// Returns `true` if a task was notified.
+    pub fn notify_one_waiter(&mut self) -> bool {
+        self.notified = true;
+        self.lock.w.lock().notify_one()
+    }
+
+    /// Notifies all tasks waiting in [`RwLock::wait_until_read`] to re-check their condition.
+    ///
+    /// The notified tasks re-check their condition once this write access is released.
+    ///
+    /// Returns `true` if at least one task was notified.
+    pub fn notify_all_waiters(&mut self) -> bool {
+        self.notified = true;
+        self.lock.w.lock().notify_all()
+    }
+}
+
 impl<T: ?Sized> Drop for OwnedRwLockWriteGuard<T> {
     fn drop(&mut self) {
-        self.lock.s.release(self.permits_acquired);
+        self.lock
+            .release_write(self.permits_acquired, self.notified);
     }
 }
 
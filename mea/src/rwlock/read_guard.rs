@@ -13,8 +13,16 @@
 // limitations under the License.
 
 use std::fmt;
+use std::future::Future;
 use std::ops::Deref;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
 
+use crate::internal;
+use crate::internal::sync::atomic::Ordering;
+use crate::rwlock::Closed;
+use crate::rwlock::MappedRwLockReadGuard;
 use crate::rwlock::RwLock;
 
 impl<T: ?Sized> RwLock<T> {
@@ -34,7 +42,14 @@ impl<T: ?Sized> RwLock<T> {
     /// # Cancel safety
     ///
     /// This method uses a queue to fairly distribute locks in the order they were requested.
-    /// Cancelling a call to `read` makes you lose your place in the queue.
+    /// Cancelling a call to `read` makes you lose your place in the queue, and gives back any
+    /// permits already assigned to it. So giving up after a timeout by racing `read` against a
+    /// timer of your runtime, e.g., with `tokio::time::timeout`, never leaks the lock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close). Use [`RwLock::read_checked`] to handle
+    /// that case.
     ///
     /// # Examples
     ///
@@ -61,8 +76,42 @@ impl<T: ?Sized> RwLock<T> {
     /// # }
     /// ```
     pub async fn read(&self) -> RwLockReadGuard<'_, T> {
-        self.s.acquire(1).await;
-        RwLockReadGuard { lock: self }
+        match self.read_checked().await {
+            Ok(guard) => guard,
+            Err(Closed) => panic!("RwLock is closed"),
+        }
+    }
+
+    /// Locks this `RwLock` with shared read access, or returns an error if the lock is closed.
+    ///
+    /// This method behaves like [`RwLock::read`], except that it returns `Err(Closed)` instead of
+    /// panicking if the lock is [closed](RwLock::close) before the read access is acquired.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute locks in the order they were requested.
+    /// Cancelling a call to `read_checked` makes you lose your place in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::Closed;
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// assert_eq!(*lock.read_checked().await.unwrap(), 1);
+    ///
+    /// lock.close();
+    /// assert_eq!(lock.read_checked().await.unwrap_err(), Closed);
+    /// # }
+    /// ```
+    pub async fn read_checked(&self) -> Result<RwLockReadGuard<'_, T>, Closed> {
+        let trace = internal::AcquireTrace::start("rwlock", &self.s, 1);
+        self.s.acquire(1).await.map_err(|_| Closed)?;
+        trace.granted(&self.s, 1);
+        Ok(RwLockReadGuard { lock: self })
     }
 
     /// Attempts to acquire this `RwLock` with shared read access.
@@ -93,6 +142,232 @@ impl<T: ?Sized> RwLock<T> {
             None
         }
     }
+
+    /// Blockingly locks this `RwLock` with shared read access.
+    ///
+    /// This method is intended for use cases where you need to use this lock in synchronous
+    /// code, e.g., in a `Drop` impl or an FFI callback. It parks the current thread until the
+    /// access is acquired.
+    ///
+    /// # Caveats
+    ///
+    /// This method must not be called from within an asynchronous execution context. Blocking
+    /// a worker thread of an async runtime stalls all the tasks scheduled on it, and deadlocks if
+    /// the current holder of the lock is one of them. This crate is runtime-agnostic, so it
+    /// cannot detect the misuse for you. Use [`read`](RwLock::read) in async code instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lock is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = Arc::new(RwLock::new(1));
+    /// let guard = lock.try_write().unwrap();
+    ///
+    /// let reader = {
+    ///     let lock = lock.clone();
+    ///     std::thread::spawn(move || *lock.blocking_read())
+    /// };
+    ///
+    /// drop(guard);
+    /// assert_eq!(reader.join().unwrap(), 1);
+    /// ```
+    pub fn blocking_read(&self) -> RwLockReadGuard<'_, T> {
+        internal::block_on(self.read())
+    }
+
+    /// Attempts to acquire this `RwLock` with shared read access, retrying up to `spins` times
+    /// before giving up.
+    ///
+    /// Unlike [`read`], this method never enqueues the caller.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let w = lock.try_write().unwrap();
+    /// assert!(lock.try_read_spin(16).is_none());
+    /// drop(w);
+    /// assert_eq!(*lock.try_read_spin(16).unwrap(), 1);
+    /// ```
+    ///
+    /// [`read`]: RwLock::read
+    pub fn try_read_spin(&self, spins: u32) -> Option<RwLockReadGuard<'_, T>> {
+        internal::spin_try(spins, || self.try_read())
+    }
+
+    /// Locks this `RwLock` with shared read access once `condition` holds for the protected data.
+    ///
+    /// The condition is checked under the read lock. If it does not hold, the read lock is
+    /// released and the calling task yields until a writer releases the lock, at which point the
+    /// condition is checked again. A writer can choose which waiting tasks re-check the condition
+    /// with [`notify_one_waiter`] and [`notify_all_waiters`]. Since a writer can only change the
+    /// data after this task releases its read lock, no write can be missed between the check and
+    /// the wait.
+    ///
+    /// Returns an RAII guard which will drop this read access of the `RwLock` when dropped.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute locks in the order they were requested.
+    /// Cancelling a call to `wait_until_read` makes you lose your place in the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close), including while waiting for a write. Use
+    /// [`RwLock::wait_until_read_checked`] to handle that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = Arc::new(RwLock::new(Vec::new()));
+    /// let lock_clone = lock.clone();
+    ///
+    /// tokio::spawn(async move {
+    ///     lock_clone.write().await.push(1);
+    /// });
+    ///
+    /// let queue = lock.wait_until_read(|queue| !queue.is_empty()).await;
+    /// assert_eq!(*queue, vec![1]);
+    /// # }
+    /// ```
+    ///
+    /// [`notify_one_waiter`]: crate::rwlock::RwLockWriteGuard::notify_one_waiter
+    /// [`notify_all_waiters`]: crate::rwlock::RwLockWriteGuard::notify_all_waiters
+    pub async fn wait_until_read<F>(&self, condition: F) -> RwLockReadGuard<'_, T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        match self.wait_until_read_checked(condition).await {
+            Ok(guard) => guard,
+            Err(Closed) => panic!("RwLock is closed"),
+        }
+    }
+
+    /// Locks this `RwLock` with shared read access once `condition` holds for the protected data,
+    /// or returns an error if the lock is closed.
+    ///
+    /// This method behaves like [`RwLock::wait_until_read`], except that it returns `Err(Closed)`
+    /// instead of panicking if the lock is [closed](RwLock::close) before the condition holds,
+    /// including while waiting for a write.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute locks in the order they were requested.
+    /// Cancelling a call to `wait_until_read_checked` makes you lose your place in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::rwlock::Closed;
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = Arc::new(RwLock::new(0));
+    /// let lock_clone = lock.clone();
+    ///
+    /// let waiter = tokio::spawn(async move {
+    ///     lock_clone
+    ///         .wait_until_read_checked(|n| *n > 0)
+    ///         .await
+    ///         .map(|n| *n)
+    /// });
+    ///
+    /// lock.close();
+    /// assert_eq!(waiter.await.unwrap(), Err(Closed));
+    /// # }
+    /// ```
+    pub async fn wait_until_read_checked<F>(
+        &self,
+        mut condition: F,
+    ) -> Result<RwLockReadGuard<'_, T>, Closed>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        loop {
+            let guard = self.read_checked().await?;
+            if condition(&*guard) {
+                return Ok(guard);
+            }
+
+            let fut = AwaitWrite {
+                lock: self,
+                guard: Some(guard),
+                key: None,
+            };
+            fut.await;
+        }
+    }
+}
+
+/// A future that waits for a writer to release the lock.
+struct AwaitWrite<'a, T: ?Sized> {
+    /// The lock that we are waiting on.
+    lock: &'a RwLock<T>,
+    /// The read guard held when checking the condition.
+    /// This will be released the first time the future is polled,
+    /// after registering the context to be notified.
+    guard: Option<RwLockReadGuard<'a, T>>,
+    /// A key into the lock's [`WakerSet`](crate::internal::WakerSet).
+    key: Option<usize>,
+}
+
+impl<T: ?Sized> Future for AwaitWrite<'_, T> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut wakers = self.lock.w.lock();
+        match self.guard.take() {
+            Some(guard) => {
+                self.key = Some(wakers.insert(cx));
+                // counted before the guard is dropped, so that the next writer sees it
+                self.lock.waiting.fetch_add(1, Ordering::SeqCst);
+                drop(wakers);
+                drop(guard);
+                Poll::Pending
+            }
+            None => {
+                if let Some(key) = self.key {
+                    if wakers.remove_if_notified(key, cx) {
+                        self.key = None;
+                        self.lock.waiting.fetch_sub(1, Ordering::SeqCst);
+                        Poll::Ready(())
+                    } else {
+                        Poll::Pending
+                    }
+                } else {
+                    Poll::Ready(())
+                }
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for AwaitWrite<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            let mut wakers = self.lock.w.lock();
+            wakers.cancel(key);
+            self.lock.waiting.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
 }
 
 /// RAII structure used to release the shared read access of a lock when dropped.
@@ -100,15 +375,118 @@ impl<T: ?Sized> RwLock<T> {
 /// This structure is created by the [`RwLock::read`] method.
 #[must_use = "if unused the RwLock will immediately unlock"]
 pub struct RwLockReadGuard<'a, T: ?Sized> {
-    lock: &'a RwLock<T>,
+    pub(super) lock: &'a RwLock<T>,
 }
 
+// A read guard only ever hands out `&T`, so it behaves like `&T` with regard to auto traits.
 unsafe impl<T: ?Sized + Sync> Send for RwLockReadGuard<'_, T> {}
-unsafe impl<T: ?Sized + Send + Sync> Sync for RwLockReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for RwLockReadGuard<'_, T> {}
+
+impl<'a, T: ?Sized> RwLockReadGuard<'a, T> {
+    /// Makes a new [`MappedRwLockReadGuard`] for a component of the locked data.
+    ///
+    /// This operation cannot fail as the [`RwLockReadGuard`] passed in already locked the data.
+    /// The component may be unsized, which allows coercing the data to a trait object, e.g.,
+    /// mapping a guard of `Box<dyn Trait>` to `dyn Trait` with `|b| &**b`.
+    ///
+    /// This is an associated function that needs to be used as `RwLockReadGuard::map(...)`. A
+    /// method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fmt::Display;
+    ///
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockReadGuard;
+    ///
+    /// let lock: RwLock<Box<dyn Display>> = RwLock::new(Box::new(42));
+    /// let guard = RwLockReadGuard::map(lock.try_read().unwrap(), |b| &**b);
+    /// assert_eq!(guard.to_string(), "42");
+    /// ```
+    pub fn map<U, F>(this: Self, f: F) -> MappedRwLockReadGuard<'a, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&T) -> &U,
+    {
+        let d = f(&*this) as *const U;
+        let s = &this.lock.s;
+        std::mem::forget(this);
+        MappedRwLockReadGuard { s, d }
+    }
+
+    /// Attempts to make a new [`MappedRwLockReadGuard`] for a component of the locked data.
+    ///
+    /// If the closure returns `None`, the original guard is returned back, so the read access is
+    /// kept.
+    ///
+    /// This is an associated function that needs to be used as `RwLockReadGuard::filter_map(...)`.
+    /// A method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockReadGuard;
+    ///
+    /// let lock = RwLock::new(HashMap::from([("a", 1)]));
+    /// let guard = lock.try_read().unwrap();
+    /// let guard = RwLockReadGuard::filter_map(guard, |m| m.get("b")).unwrap_err();
+    /// let a = RwLockReadGuard::filter_map(guard, |m| m.get("a")).unwrap();
+    /// assert_eq!(*a, 1);
+    /// ```
+    pub fn filter_map<U, F>(this: Self, f: F) -> Result<MappedRwLockReadGuard<'a, U>, Self>
+    where
+        U: ?Sized,
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        match f(&*this) {
+            Some(d) => {
+                let d = d as *const U;
+                let s = &this.lock.s;
+                std::mem::forget(this);
+                Ok(MappedRwLockReadGuard { s, d })
+            }
+            None => Err(this),
+        }
+    }
+
+    /// Returns the [version](RwLock::version) of the locked data.
+    ///
+    /// No write can happen while the guard is held, so the version is stable and matches the
+    /// data the guard gives access to.
+    ///
+    /// This is an associated function that needs to be used as `RwLockReadGuard::version(...)`.
+    /// A method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockReadGuard;
+    ///
+    /// let lock = RwLock::new(vec![1]);
+    /// let guard = lock.read().await;
+    /// let seen = RwLockReadGuard::version(&guard);
+    /// drop(guard);
+    ///
+    /// // nothing changed, so a cached computation on the data can be reused
+    /// assert_eq!(RwLockReadGuard::version(&lock.read().await), seen);
+    /// # }
+    /// ```
+    pub fn version(this: &Self) -> u64 {
+        this.lock.version()
+    }
+}
 
 impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
     fn drop(&mut self) {
         self.lock.s.release(1);
+        internal::trace_release("rwlock", &self.lock.s, 1);
     }
 }
 
@@ -0,0 +1,798 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio_test::assert_pending;
+use tokio_test::assert_ready;
+use tokio_test::task::spawn;
+
+use crate::rwlock::Closed;
+use crate::rwlock::LocalRwLock;
+use crate::rwlock::LockState;
+use crate::rwlock::MappedRwLockReadGuard;
+use crate::rwlock::RwLock;
+use crate::rwlock::RwLockReadGuard;
+use crate::rwlock::RwLockUpgradableReadGuard;
+use crate::rwlock::RwLockWriteGuard;
+
+#[tokio::test]
+async fn wait_until_read_wakes_on_write() {
+    let lock = Arc::new(RwLock::new(Vec::new()));
+
+    let consumer = {
+        let lock = lock.clone();
+        tokio::spawn(async move {
+            let queue = lock.wait_until_read(|queue| !queue.is_empty()).await;
+            queue[0]
+        })
+    };
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    lock.write().await.push(42);
+    assert_eq!(consumer.await.unwrap(), 42);
+}
+
+#[test]
+fn wait_until_read_rechecks_after_each_write() {
+    let lock = RwLock::new(0);
+    let mut f = spawn(lock.wait_until_read(|n| *n >= 2));
+    assert_pending!(f.poll());
+
+    *lock.try_write().unwrap() += 1;
+    assert!(f.is_woken());
+    assert_pending!(f.poll());
+
+    // readers do not notify
+    drop(lock.try_read().unwrap());
+    assert!(!f.is_woken());
+
+    *lock.try_write().unwrap() += 1;
+    assert!(f.is_woken());
+    let n = assert_ready!(f.poll());
+    assert_eq!(*n, 2);
+}
+
+#[test]
+fn wait_until_read_waiters_are_counted() {
+    let lock = RwLock::new(0);
+    let mut f = spawn(lock.wait_until_read(|n| *n > 0));
+    let mut cancelled = spawn(lock.wait_until_read(|n| *n > 1));
+    assert_pending!(f.poll());
+    assert_pending!(cancelled.poll());
+    assert_eq!(lock.waiting.load(Ordering::SeqCst), 2);
+
+    drop(cancelled);
+    assert_eq!(lock.waiting.load(Ordering::SeqCst), 1);
+    *lock.try_write().unwrap() += 1;
+    drop(assert_ready!(f.poll()));
+    assert_eq!(lock.waiting.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn with_parallelism_admits_parallel_readers() {
+    let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let lock = RwLock::with_parallelism(0);
+    let guards = (0..parallelism)
+        .map(|_| lock.try_read().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(guards.len(), parallelism);
+}
+
+#[tokio::test]
+async fn write_all_in_opposite_orders() {
+    let locks = Arc::new([RwLock::new(0), RwLock::new(0), RwLock::new(0)]);
+
+    let handles = [[0, 1, 2], [2, 1, 0]].map(|order| {
+        let locks = locks.clone();
+        tokio::spawn(async move {
+            for _ in 0..1000 {
+                let input = order.map(|i| &locks[i]);
+                let mut guards = crate::rwlock::write_all(&input).await;
+                for (n, g) in guards.iter_mut().enumerate() {
+                    **g += n;
+                }
+                tokio::task::yield_now().await;
+            }
+        })
+    });
+
+    for handle in handles {
+        tokio::time::timeout(Duration::from_secs(10), handle)
+            .await
+            .expect("write_all deadlocked")
+            .unwrap();
+    }
+    assert_eq!(*locks[0].read().await, 2000);
+    assert_eq!(*locks[1].read().await, 2000);
+    assert_eq!(*locks[2].read().await, 2000);
+}
+
+#[test]
+#[should_panic]
+fn write_all_duplicated_lock() {
+    let lock = RwLock::new(0);
+    let _ = pollster::block_on(crate::rwlock::write_all(&[&lock, &lock]));
+}
+
+#[tokio::test]
+async fn concurrent_fetch_update() {
+    let lock = Arc::new(RwLock::new(0u64));
+    let handles = (0..8)
+        .map(|_| {
+            let lock = lock.clone();
+            tokio::spawn(async move {
+                for _ in 0..100 {
+                    lock.fetch_update(|n| n + 1).await;
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    assert_eq!(lock.load_copy().await, 800);
+}
+
+#[test]
+fn close_wakes_parked_read() {
+    let lock = RwLock::new(1);
+    let w = lock.try_write().unwrap();
+
+    let mut read = spawn(lock.read_checked());
+    assert_pending!(read.poll());
+
+    lock.close();
+    assert!(read.is_woken());
+    assert_eq!(assert_ready!(read.poll()).unwrap_err(), Closed);
+
+    // the write guard held before closing is still valid
+    drop(w);
+    assert!(lock.try_read().is_none());
+}
+
+#[test]
+fn close_fails_parked_write_cleanly() {
+    let lock = RwLock::with_max_readers(1, 4);
+    let r = lock.try_read().unwrap();
+
+    // the writer is assigned the 3 permits left and waits for the last one
+    let mut write = spawn(lock.write_checked());
+    assert_pending!(write.poll());
+    assert_eq!(lock.s.available_permits(), 0);
+
+    lock.close();
+    assert_eq!(assert_ready!(write.poll()).unwrap_err(), Closed);
+    assert_eq!(lock.s.available_permits(), 3);
+
+    drop(r);
+    assert_eq!(lock.s.available_permits(), 4);
+}
+
+#[test]
+fn local_rwlock_borrows() {
+    let lock = LocalRwLock::new(1);
+
+    let r1 = lock.read();
+    let r2 = lock.try_read().unwrap();
+    assert!(lock.try_write().is_none());
+    drop((r1, r2));
+
+    let mut w = lock.write();
+    *w += 1;
+    assert!(lock.try_read().is_none());
+    assert!(lock.try_write().is_none());
+    drop(w);
+
+    assert_eq!(*lock.read(), 2);
+}
+
+#[test]
+#[should_panic(expected = "already locked for writing")]
+fn local_rwlock_read_while_writing() {
+    let lock = LocalRwLock::new(1);
+    let _w = lock.write();
+    let _r = lock.read();
+}
+
+#[test]
+#[should_panic(expected = "already locked")]
+fn local_rwlock_write_while_reading() {
+    let lock = LocalRwLock::new(1);
+    let _r = lock.read();
+    let _w = lock.write();
+}
+
+#[test]
+fn notify_one_waiter_wakes_only_one() {
+    let lock = RwLock::new(0);
+    let evals: Vec<_> = (0..3).map(|_| AtomicUsize::new(0)).collect();
+
+    let mut waiters: Vec<_> = evals
+        .iter()
+        .map(|evals| {
+            spawn(lock.wait_until_read(move |v| {
+                evals.fetch_add(1, Ordering::SeqCst);
+                *v > 0
+            }))
+        })
+        .collect();
+    for waiter in waiters.iter_mut() {
+        assert_pending!(waiter.poll());
+    }
+
+    let mut w = lock.try_write().unwrap();
+    *w = 1;
+    assert!(w.notify_one_waiter());
+    drop(w);
+
+    let woken: Vec<_> = waiters.iter().map(|w| w.is_woken()).collect();
+    assert_eq!(woken.iter().filter(|&&woken| woken).count(), 1);
+    for (waiter, woken) in waiters.iter_mut().zip(woken) {
+        if woken {
+            assert_eq!(*assert_ready!(waiter.poll()), 1);
+        } else {
+            assert_pending!(waiter.poll());
+        }
+    }
+    let evals: Vec<_> = evals.iter().map(|e| e.load(Ordering::SeqCst)).collect();
+    assert_eq!(evals.iter().sum::<usize>(), 4);
+}
+
+#[test]
+fn map_read_guard_to_trait_object() {
+    let calls = AtomicUsize::new(0);
+    let lock: RwLock<Box<dyn Fn() + Sync + '_>> = RwLock::new(Box::new(|| {
+        calls.fetch_add(1, Ordering::SeqCst);
+    }));
+
+    let f = RwLockReadGuard::map(lock.try_read().unwrap(), |b| &**b);
+    let boxed = RwLockReadGuard::map(lock.try_read().unwrap(), |b| b);
+    let g = MappedRwLockReadGuard::map(boxed, |b| &**b);
+    f();
+    g();
+    assert!(lock.try_write().is_none());
+
+    drop((f, g));
+    assert!(lock.try_write().is_some());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn replace_and_take_swap_values() {
+    let lock = RwLock::new(String::from("old"));
+    assert_eq!(lock.replace(String::from("new")).await, "old");
+    assert_eq!(*lock.read().await, "new");
+    assert!(lock.try_write().is_some());
+
+    assert_eq!(lock.take().await, "new");
+    assert_eq!(*lock.read().await, "");
+    assert!(lock.try_write().is_some());
+}
+
+#[test]
+fn try_spin_succeeds_when_released_within_budget() {
+    let lock = Arc::new(RwLock::new(0));
+    let w = lock.clone().try_write_owned().unwrap();
+    let holder = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        drop(w);
+    });
+    *lock.try_write_spin(u32::MAX).unwrap() += 1;
+    holder.join().unwrap();
+
+    let r = lock.clone().try_read_owned().unwrap();
+    let holder = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        drop(r);
+    });
+    assert!(lock.try_read_spin(0).is_some());
+    *lock.try_write_spin(u32::MAX).unwrap() += 1;
+    holder.join().unwrap();
+    assert_eq!(*lock.try_read_spin(0).unwrap(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn owned_write_guard_released_from_blocking_thread() {
+    fn assert_send_static<T: Send + 'static>(_: &T) {}
+
+    let lock = Arc::new(RwLock::new(Vec::new()));
+    let guard = lock.clone().write_owned().await;
+    assert_send_static(&guard);
+
+    let reader = {
+        let lock = lock.clone();
+        tokio::spawn(async move { lock.read().await.clone() })
+    };
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!reader.is_finished());
+
+    // mutate and release the lock from a plain thread, which must wake the async reader
+    let worker = std::thread::spawn(move || {
+        let mut guard = guard;
+        guard.push(42);
+        drop(guard);
+    });
+    worker.join().unwrap();
+
+    assert_eq!(reader.await.unwrap(), vec![42]);
+    assert!(lock.try_write().is_some());
+}
+
+#[tokio::test]
+async fn version_advances_once_per_write() {
+    let lock = Arc::new(RwLock::new(0));
+    assert_eq!(lock.version(), 0);
+
+    let r1 = lock.read().await;
+    let r2 = lock.read().await;
+    assert_eq!(RwLockReadGuard::version(&r1), 0);
+    assert_eq!(RwLockReadGuard::version(&r2), 0);
+    drop((r1, r2));
+    assert_eq!(lock.version(), 0);
+
+    *lock.write().await += 1;
+    assert_eq!(lock.version(), 1);
+    *lock.clone().write_owned().await += 1;
+    assert_eq!(lock.version(), 2);
+    *lock.try_write().unwrap() += 1;
+    assert_eq!(RwLockReadGuard::version(&lock.read().await), 3);
+}
+
+#[tokio::test]
+#[cfg(feature = "unstable-internals")]
+async fn rwlock_from_semaphore() {
+    let lock = RwLock::from_semaphore(crate::internals::Semaphore::new(2), 0, 2);
+    let r1 = lock.read().await;
+    let r2 = lock.read().await;
+    assert!(lock.try_read().is_none());
+    drop((r1, r2));
+    *lock.write().await += 1;
+    assert_eq!(*lock.read().await, 1);
+}
+
+#[test]
+#[cfg(feature = "unstable-internals")]
+fn rwlock_from_barging_semaphore() {
+    let lock = RwLock::from_semaphore(crate::internals::Semaphore::barging(2), 0, 2);
+    let r1 = lock.try_read().unwrap();
+    let mut writer = spawn(async { *lock.write().await += 1 });
+    assert_pending!(writer.poll());
+
+    // the pending writer holds no permit, so a new reader can still barge in
+    let r2 = lock.try_read().unwrap();
+    drop(r1);
+    assert_pending!(writer.poll());
+    drop(r2);
+    assert!(writer.is_woken());
+    assert_ready!(writer.poll());
+    assert_eq!(*lock.try_read().unwrap(), 1);
+}
+
+#[test]
+#[cfg(feature = "unstable-internals")]
+#[should_panic(expected = "exactly `max_readers` permits")]
+fn rwlock_from_semaphore_rejects_mismatched_permits() {
+    RwLock::from_semaphore(crate::internals::Semaphore::new(4), 0, 2);
+}
+
+#[tokio::test]
+async fn optimistic_update_retries_on_conflict() {
+    let lock = RwLock::new(1);
+    let mut conflicted = false;
+    let calls = lock
+        .optimistic_update(|n| {
+            if !conflicted {
+                // another writer sneaks in while the new value is computed
+                conflicted = true;
+                *lock.try_write().unwrap() += 10;
+            }
+            n * 2
+        })
+        .await;
+
+    assert_eq!(calls, 2);
+    assert_eq!(*lock.read().await, 22);
+    // the sneaked write and the update, but not the discarded attempt
+    assert_eq!(lock.version(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn optimistic_update_loses_no_write() {
+    let lock = Arc::new(RwLock::new(0u32));
+    let handles = (0..8)
+        .map(|_| {
+            let lock = lock.clone();
+            tokio::spawn(async move {
+                for _ in 0..50 {
+                    lock.optimistic_update(|n| n + 1).await;
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    assert_eq!(*lock.read().await, 400);
+}
+
+#[test]
+fn upgrade_keeps_queued_writer_out() {
+    let lock = RwLock::with_max_readers(0, 4);
+    let upgradable = lock.try_upgradable_read().unwrap();
+    let reader = lock.try_read().unwrap();
+
+    // the queued writer is handed the free read permits
+    let mut writer = spawn(lock.write());
+    assert_pending!(writer.poll());
+    assert!(lock.try_upgradable_read().is_none());
+
+    // the upgrade waits ahead of the writer, and takes those permits back
+    let mut upgrade = spawn(RwLockUpgradableReadGuard::upgrade(upgradable));
+    assert_pending!(upgrade.poll());
+    drop(reader);
+    let mut upgraded = assert_ready!(upgrade.poll());
+    assert_pending!(writer.poll());
+    *upgraded += 1;
+    drop(upgraded);
+
+    let mut written = assert_ready!(writer.poll());
+    assert_eq!(*written, 1);
+    *written += 1;
+    drop(written);
+    assert_eq!(*lock.try_read().unwrap(), 2);
+    assert_eq!(lock.version(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn upgrades_and_writers_do_not_deadlock() {
+    let lock = Arc::new(RwLock::with_max_readers(0u32, 4));
+    let handles = (0..12)
+        .map(|i| {
+            let lock = lock.clone();
+            tokio::spawn(async move {
+                for _ in 0..100 {
+                    match i % 3 {
+                        0 => *lock.write().await += 1,
+                        1 => {
+                            let guard = lock.upgradable_read().await;
+                            tokio::task::yield_now().await;
+                            *RwLockUpgradableReadGuard::upgrade(guard).await += 1;
+                        }
+                        _ => {
+                            let _guard = lock.read().await;
+                            tokio::task::yield_now().await;
+                        }
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    assert_eq!(*lock.read().await, 800);
+}
+
+#[test]
+fn queued_writers_and_readers_are_granted_in_order() {
+    let lock = RwLock::new(Vec::new());
+    let w1 = lock.try_write().unwrap();
+
+    let mut w2 = spawn(async { lock.write().await.push("w2") });
+    let mut w3 = spawn(async { lock.write().await.push("w3") });
+    let mut r = spawn(async { lock.read().await.len() });
+    assert_pending!(w2.poll());
+    assert_pending!(w3.poll());
+    assert_pending!(r.poll());
+
+    drop(w1);
+    assert_ready!(w2.poll());
+    assert_pending!(r.poll());
+    assert_ready!(w3.poll());
+    assert_eq!(assert_ready!(r.poll()), 2);
+    assert_eq!(*lock.try_read().unwrap(), vec!["w2", "w3"]);
+}
+
+#[test]
+fn upgradable_read_released_on_drop() {
+    let lock = RwLock::with_max_readers(0, 2);
+    let upgradable = lock.try_upgradable_read().unwrap();
+    let mut next = spawn(lock.upgradable_read());
+    assert_pending!(next.poll());
+
+    drop(upgradable);
+    let upgradable = assert_ready!(next.poll());
+    drop(upgradable);
+    assert!(lock.try_write().is_some());
+}
+
+#[test]
+fn downgrade_keeps_queued_writer_out() {
+    let lock = RwLock::with_max_readers(0, 4);
+    let mut guard = lock.try_write().unwrap();
+    *guard = 1;
+
+    let mut writer = spawn(lock.write());
+    assert_pending!(writer.poll());
+
+    let reader = RwLockWriteGuard::downgrade(guard);
+    assert_eq!(lock.version(), 1);
+    assert_pending!(writer.poll());
+    assert_eq!(*reader, 1);
+
+    drop(reader);
+    let mut written = assert_ready!(writer.poll());
+    *written = 2;
+    drop(written);
+    assert_eq!(*lock.try_read().unwrap(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn owned_read_guards_outlive_the_spawning_scope() {
+    let lock = Arc::new(RwLock::new(7));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let guard = lock.clone().try_read_owned().unwrap();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                *guard
+            })
+        })
+        .collect();
+    assert!(lock.try_write().is_none());
+
+    for handle in handles {
+        assert_eq!(handle.await.unwrap(), 7);
+    }
+    *lock.clone().write_owned().await += 1;
+    assert_eq!(*lock.read().await, 8);
+}
+
+#[test]
+fn failed_try_owned_drops_its_arc() {
+    let lock = Arc::new(RwLock::new(0));
+    let writer = lock.clone().try_write_owned().unwrap();
+    assert_eq!(Arc::strong_count(&lock), 2);
+
+    assert!(lock.clone().try_read_owned().is_none());
+    assert!(lock.clone().try_write_owned().is_none());
+    assert_eq!(Arc::strong_count(&lock), 2);
+
+    drop(writer);
+    assert_eq!(Arc::strong_count(&lock), 1);
+    let reader = lock.clone().try_read_owned().unwrap();
+    assert!(lock.clone().try_write_owned().is_none());
+    assert_eq!(Arc::strong_count(&lock), 2);
+    drop(reader);
+    assert_eq!(Arc::strong_count(&lock), 1);
+}
+
+#[test]
+fn mapped_read_guard_holds_one_permit() {
+    #[derive(Debug)]
+    struct Config {
+        name: String,
+        values: Vec<u32>,
+    }
+
+    let lock = RwLock::with_max_readers(
+        Config {
+            name: "mea".to_string(),
+            values: vec![1, 2, 3],
+        },
+        2,
+    );
+    let name = RwLockReadGuard::map(lock.try_read().unwrap(), |c| &c.name);
+    assert_eq!(name.as_str(), "mea");
+
+    let mut writer = spawn(lock.write());
+    assert_pending!(writer.poll());
+    drop(name);
+    assert!(writer.is_woken());
+    let config = assert_ready!(writer.poll());
+    assert_eq!(config.values, [1, 2, 3]);
+}
+
+#[test]
+fn mapped_write_guard_releases_as_a_writer() {
+    let lock = RwLock::new((0, vec![0; 4]));
+    let mut f = spawn(lock.wait_until_read(|(_, buf)| buf.iter().all(|&b| b == 1)));
+    assert_pending!(f.poll());
+
+    let mut buf = RwLockWriteGuard::map(lock.try_write().unwrap(), |v| &mut v.1[..]);
+    buf.fill(1);
+    assert!(lock.try_read().is_none());
+    assert!(!f.is_woken());
+
+    drop(buf);
+    assert_eq!(lock.version(), 1);
+    assert!(f.is_woken());
+    let v = assert_ready!(f.poll());
+    assert_eq!(v.1, [1; 4]);
+}
+
+#[test]
+fn filter_map_keeps_the_lock_on_none() {
+    let lock = RwLock::new(vec![1, 2, 3]);
+    let guard = lock.try_write().unwrap();
+    let guard = RwLockWriteGuard::filter_map(guard, |v| v.get_mut(3)).unwrap_err();
+    assert!(lock.try_read().is_none());
+    assert_eq!(lock.version(), 0);
+
+    let mut last = RwLockWriteGuard::filter_map(guard, |v| v.last_mut()).unwrap();
+    *last = 4;
+    assert!(lock.try_read().is_none());
+    drop(last);
+    assert_eq!(lock.version(), 1);
+
+    let guard = lock.try_read().unwrap();
+    let guard = RwLockReadGuard::filter_map(guard, |v| v.get(3)).unwrap_err();
+    assert!(lock.try_write().is_none());
+    let first = RwLockReadGuard::filter_map(guard, |v| v.first()).unwrap();
+    assert_eq!(*first, 1);
+    assert!(lock.try_write().is_none());
+    drop(first);
+    assert_eq!(*lock.try_write().unwrap(), [1, 2, 4]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn blocking_access_from_plain_threads() {
+    let lock = Arc::new(RwLock::new(0));
+    let guard = lock.clone().write_owned().await;
+
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                if i % 2 == 0 {
+                    *lock.blocking_write() += 1;
+                } else {
+                    assert!(*lock.blocking_read() >= 0);
+                }
+            })
+        })
+        .collect();
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(threads.iter().all(|t| !t.is_finished()));
+    drop(guard);
+
+    *lock.write().await += 1;
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(*lock.read().await, 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn writers_timed_out_by_runtime_timer_leak_nothing() {
+    const MAX_READERS: u32 = 8;
+
+    let lock = Arc::new(RwLock::with_max_readers(0, MAX_READERS));
+    let done = Arc::new(AtomicUsize::new(0));
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let lock = lock.clone();
+            let done = done.clone();
+            tokio::spawn(async move {
+                while done.load(Ordering::SeqCst) == 0 {
+                    let guard = lock.read().await;
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    drop(guard);
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+
+    let mut timed_out = 0u32;
+    for _ in 0..50 {
+        let write = tokio::time::timeout(Duration::from_millis(1), lock.write()).await;
+        match write {
+            Ok(mut guard) => *guard += 1,
+            Err(_) => timed_out += 1,
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    done.store(1, Ordering::SeqCst);
+    for reader in readers {
+        reader.await.unwrap();
+    }
+    assert!(timed_out > 0);
+    assert_eq!(*lock.try_read().unwrap() + timed_out, 50);
+
+    // all permits are back: every reader slot is free, and so is the writer slot
+    let guards: Vec<_> = (0..MAX_READERS).map(|_| lock.try_read().unwrap()).collect();
+    assert!(lock.try_read().is_none());
+    drop(guards);
+    assert!(lock.try_write().is_some());
+}
+
+#[tokio::test]
+async fn set_and_try_variants_write_under_the_lock() {
+    let lock = RwLock::new(String::from("a"));
+    lock.set(String::from("b")).await;
+    assert_eq!(lock.version(), 1);
+
+    let guard = lock.read().await;
+    assert_eq!(lock.try_replace(String::from("c")).unwrap_err(), "c");
+    assert_eq!(lock.try_set(String::from("d")).unwrap_err(), "d");
+    drop(guard);
+    assert_eq!(lock.version(), 1);
+
+    assert_eq!(lock.try_replace(String::from("c")).unwrap(), "b");
+    assert!(lock.try_set(String::from("d")).is_ok());
+    assert_eq!(lock.version(), 3);
+    assert_eq!(*lock.read().await, "d");
+}
+
+#[test]
+fn lock_state_ignores_permits_of_a_waiting_writer() {
+    let lock = RwLock::with_max_readers(0, 4);
+    let r1 = lock.try_read().unwrap();
+    let r2 = lock.try_read().unwrap();
+    assert_eq!(lock.lock_state(), LockState::Read(2));
+
+    // the writer is handed the two free permits while waiting for the readers
+    let mut writer = spawn(lock.write());
+    assert_pending!(writer.poll());
+    assert_eq!(lock.lock_state(), LockState::Read(2));
+
+    drop(r1);
+    assert_eq!(lock.lock_state(), LockState::Read(1));
+    drop(r2);
+    let w = assert_ready!(writer.poll());
+    assert_eq!(lock.lock_state(), LockState::Write);
+    let mapped = RwLockWriteGuard::map(w, |n| n);
+    assert_eq!(lock.lock_state(), LockState::Write);
+    drop(mapped);
+    assert_eq!(lock.lock_state(), LockState::Free);
+
+    // all reader slots taken is not a write
+    let readers: Vec<_> = (0..4).map(|_| lock.try_read().unwrap()).collect();
+    assert_eq!(lock.lock_state(), LockState::Read(4));
+    let mut writer = spawn(lock.write());
+    assert_pending!(writer.poll());
+    drop(writer);
+    assert_eq!(lock.lock_state(), LockState::Read(4));
+    drop(readers);
+    assert_eq!(lock.lock_state(), LockState::Free);
+
+    // a cancelled writer gives back the permits handed to it
+    let r = lock.try_read().unwrap();
+    let mut writer = spawn(lock.write());
+    assert_pending!(writer.poll());
+    drop(writer);
+    assert_eq!(lock.lock_state(), LockState::Read(1));
+    drop(r);
+    assert_eq!(lock.lock_state(), LockState::Free);
+}
+
+#[test]
+fn close_fails_wait_until_read_checked() {
+    let lock = RwLock::new(0);
+    let mut f = spawn(lock.wait_until_read_checked(|n| *n > 0));
+    assert_pending!(f.poll());
+
+    lock.close();
+    assert!(f.is_woken());
+    assert_eq!(assert_ready!(f.poll()).unwrap_err(), Closed);
+}
@@ -0,0 +1,235 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::ops::Deref;
+
+use crate::internal;
+use crate::internal::sync::atomic::Ordering;
+use crate::rwlock::RwLock;
+use crate::rwlock::RwLockWriteGuard;
+use crate::rwlock::UpgradeGate;
+
+impl<T: ?Sized> RwLock<T> {
+    /// Locks this `RwLock` with upgradable read access, causing the current task to yield until
+    /// the lock has been acquired.
+    ///
+    /// An upgradable reader shares the lock with plain readers, but excludes writers and other
+    /// upgradable readers. It can later be [upgraded](RwLockUpgradableReadGuard::upgrade) to a
+    /// writer without releasing the lock in between, so no other writer can change the data
+    /// after it was read.
+    ///
+    /// Returns an RAII guard which will drop the upgradable read access of this `RwLock` when
+    /// dropped.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute locks in the order they were requested.
+    /// Cancelling a call to `upgradable_read` makes you lose your place in the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockUpgradableReadGuard;
+    ///
+    /// let lock = RwLock::new(vec![1]);
+    /// let guard = lock.upgradable_read().await;
+    ///
+    /// // plain readers are still allowed
+    /// assert_eq!(*lock.read().await, vec![1]);
+    ///
+    /// if !guard.contains(&2) {
+    ///     let mut guard = RwLockUpgradableReadGuard::upgrade(guard).await;
+    ///     guard.push(2);
+    /// }
+    /// assert_eq!(*lock.read().await, vec![1, 2]);
+    /// # }
+    /// ```
+    pub async fn upgradable_read(&self) -> RwLockUpgradableReadGuard<'_, T> {
+        if self.u.acquire(1).await.is_err() {
+            panic!("RwLock is closed");
+        }
+        let gate = UpgradeGate(&self.u);
+        let trace = internal::AcquireTrace::start("rwlock", &self.s, 1);
+        if self.s.acquire(1).await.is_err() {
+            panic!("RwLock is closed");
+        }
+        trace.granted(&self.s, 1);
+        std::mem::forget(gate);
+        RwLockUpgradableReadGuard { lock: self }
+    }
+
+    /// Attempts to acquire this `RwLock` with upgradable read access.
+    ///
+    /// If the access couldn't be acquired immediately, returns `None`. Otherwise, an RAII guard is
+    /// returned which will release upgradable read access when dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let guard = lock.try_upgradable_read().unwrap();
+    /// assert!(lock.try_upgradable_read().is_none());
+    /// assert!(lock.try_write().is_none());
+    /// assert_eq!(*lock.try_read().unwrap(), 1);
+    /// ```
+    pub fn try_upgradable_read(&self) -> Option<RwLockUpgradableReadGuard<'_, T>> {
+        if !self.u.try_acquire(1) {
+            return None;
+        }
+        let gate = UpgradeGate(&self.u);
+        if !self.s.try_acquire(1) {
+            return None;
+        }
+        std::mem::forget(gate);
+        Some(RwLockUpgradableReadGuard { lock: self })
+    }
+}
+
+/// RAII structure used to release the upgradable read access of a lock when dropped.
+///
+/// This structure is created by the [`RwLock::upgradable_read`] method.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockUpgradableReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+// An upgradable read guard can become a write guard, so it has the same bounds.
+unsafe impl<T: ?Sized + Send + Sync> Send for RwLockUpgradableReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLockUpgradableReadGuard<'_, T> {}
+
+impl<'a, T: ?Sized> RwLockUpgradableReadGuard<'a, T> {
+    /// Upgrades the upgradable read access to write access, waiting for the other readers to
+    /// release the lock.
+    ///
+    /// The lock is not released in between, so no other writer can access the data first. The
+    /// upgrade waits ahead of the tasks already waiting for the lock.
+    ///
+    /// This is an associated function that needs to be used as
+    /// `RwLockUpgradableReadGuard::upgrade(...)`. A method would interfere with methods of the
+    /// same name on the contents of the locked data.
+    ///
+    /// # Cancel safety
+    ///
+    /// Cancelling a call to `upgrade` drops the guard, which releases the upgradable read access.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close) while waiting for the other readers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockUpgradableReadGuard;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let guard = lock.upgradable_read().await;
+    /// let mut guard = RwLockUpgradableReadGuard::upgrade(guard).await;
+    /// *guard += 1;
+    /// drop(guard);
+    /// assert_eq!(*lock.read().await, 2);
+    /// # }
+    /// ```
+    pub async fn upgrade(this: Self) -> RwLockWriteGuard<'a, T> {
+        let lock = this.lock;
+        // A writer queued earlier waits for the read permit this guard holds, so waiting behind it
+        // would never end. Wait ahead of it instead, taking back what it was handed so far.
+        if lock.s.acquire_front(lock.max_readers - 1).await.is_err() {
+            panic!("RwLock is closed");
+        }
+        Self::into_write_guard(this)
+    }
+
+    /// Attempts to upgrade the upgradable read access to write access.
+    ///
+    /// If other readers still hold the lock, returns the guard back in `Err`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockUpgradableReadGuard;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let guard = lock.try_upgradable_read().unwrap();
+    ///
+    /// let reader = lock.try_read().unwrap();
+    /// let guard = RwLockUpgradableReadGuard::try_upgrade(guard).unwrap_err();
+    /// drop(reader);
+    ///
+    /// let mut guard = RwLockUpgradableReadGuard::try_upgrade(guard).unwrap();
+    /// *guard = 2;
+    /// ```
+    pub fn try_upgrade(this: Self) -> Result<RwLockWriteGuard<'a, T>, Self> {
+        let lock = this.lock;
+        if lock.s.try_acquire(lock.max_readers - 1) {
+            Ok(Self::into_write_guard(this))
+        } else {
+            Err(this)
+        }
+    }
+
+    /// Converts the guard into a write guard, once it holds all the permits.
+    fn into_write_guard(this: Self) -> RwLockWriteGuard<'a, T> {
+        let lock = this.lock;
+        std::mem::forget(this);
+        lock.writing.store(true, Ordering::Release);
+        // other upgradable readers may proceed to wait for the write guard
+        lock.u.release(1);
+        RwLockWriteGuard {
+            permits_acquired: lock.max_readers,
+            notified: false,
+            lock,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockUpgradableReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.s.release(1);
+        internal::trace_release("rwlock", &self.lock.s, 1);
+        self.lock.u.release(1);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockUpgradableReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for RwLockUpgradableReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.c.get() }
+    }
+}
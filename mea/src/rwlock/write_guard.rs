@@ -16,7 +16,11 @@ use std::fmt;
 use std::ops::Deref;
 use std::ops::DerefMut;
 
+use crate::internal;
+use crate::rwlock::Closed;
+use crate::rwlock::MappedRwLockWriteGuard;
 use crate::rwlock::RwLock;
+use crate::rwlock::RwLockReadGuard;
 
 impl<T: ?Sized> RwLock<T> {
     /// Locks this `RwLock` with exclusive write access, causing the current task to yield until the
@@ -30,7 +34,14 @@ impl<T: ?Sized> RwLock<T> {
     /// # Cancel safety
     ///
     /// This method uses a queue to fairly distribute locks in the order they were requested.
-    /// Cancelling a call to `write` makes you lose your place in the queue.
+    /// Cancelling a call to `write` makes you lose your place in the queue, and gives back any
+    /// permits already assigned to it. So giving up after a timeout by racing `write` against a
+    /// timer of your runtime, e.g., with `tokio::time::timeout`, never leaks the lock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `RwLock` is [closed](RwLock::close). Use [`RwLock::write_checked`] to handle
+    /// that case.
     ///
     /// # Examples
     ///
@@ -45,11 +56,88 @@ impl<T: ?Sized> RwLock<T> {
     /// # }
     /// ```
     pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
-        self.s.acquire(self.max_readers).await;
-        RwLockWriteGuard {
+        match self.write_checked().await {
+            Ok(guard) => guard,
+            Err(Closed) => panic!("RwLock is closed"),
+        }
+    }
+
+    /// Locks this `RwLock` with exclusive write access, or returns an error if the lock is closed.
+    ///
+    /// This method behaves like [`RwLock::write`], except that it returns `Err(Closed)` instead
+    /// of panicking if the lock is [closed](RwLock::close) before the write access is acquired.
+    /// A writer that is still waiting when the lock is closed gives back any read permits it has
+    /// been assigned, so the lock is never left in a locked state.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute locks in the order they were requested.
+    /// Cancelling a call to `write_checked` makes you lose your place in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::Closed;
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// *lock.write_checked().await.unwrap() = 2;
+    ///
+    /// lock.close();
+    /// assert_eq!(lock.write_checked().await.unwrap_err(), Closed);
+    /// # }
+    /// ```
+    pub async fn write_checked(&self) -> Result<RwLockWriteGuard<'_, T>, Closed> {
+        let trace = internal::AcquireTrace::start("rwlock", &self.s, self.max_readers);
+        self.acquire_write().await?;
+        trace.granted(&self.s, self.max_readers);
+        Ok(RwLockWriteGuard {
             permits_acquired: self.max_readers,
+            notified: false,
             lock: self,
-        }
+        })
+    }
+
+    /// Blockingly locks this `RwLock` with exclusive write access.
+    ///
+    /// This method is intended for use cases where you need to use this lock in synchronous
+    /// code, e.g., in a `Drop` impl or an FFI callback. It parks the current thread until the
+    /// access is acquired.
+    ///
+    /// # Caveats
+    ///
+    /// This method must not be called from within an asynchronous execution context. Blocking
+    /// a worker thread of an async runtime stalls all the tasks scheduled on it, and deadlocks if
+    /// the current holder of the lock is one of them. This crate is runtime-agnostic, so it
+    /// cannot detect the misuse for you. Use [`write`](RwLock::write) in async code instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lock is [closed](RwLock::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = Arc::new(RwLock::new(1));
+    /// let guard = lock.try_read().unwrap();
+    ///
+    /// let writer = {
+    ///     let lock = lock.clone();
+    ///     std::thread::spawn(move || *lock.blocking_write() += 1)
+    /// };
+    ///
+    /// drop(guard);
+    /// writer.join().unwrap();
+    /// assert_eq!(*lock.try_read().unwrap(), 2);
+    /// ```
+    pub fn blocking_write(&self) -> RwLockWriteGuard<'_, T> {
+        internal::block_on(self.write())
     }
 
     /// Attempts to acquire this `RwLock` with exclusive write access.
@@ -74,15 +162,38 @@ impl<T: ?Sized> RwLock<T> {
     /// *v = 2;
     /// ```
     pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
-        if self.s.try_acquire(self.max_readers) {
+        if self.try_acquire_write() {
             Some(RwLockWriteGuard {
                 permits_acquired: self.max_readers,
+                notified: false,
                 lock: self,
             })
         } else {
             None
         }
     }
+
+    /// Attempts to acquire this `RwLock` with exclusive write access, retrying up to `spins`
+    /// times before giving up.
+    ///
+    /// Unlike [`write`], this method never enqueues the caller.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let r = lock.try_read().unwrap();
+    /// assert!(lock.try_write_spin(16).is_none());
+    /// drop(r);
+    /// *lock.try_write_spin(16).unwrap() = 2;
+    /// ```
+    ///
+    /// [`write`]: RwLock::write
+    pub fn try_write_spin(&self, spins: u32) -> Option<RwLockWriteGuard<'_, T>> {
+        internal::spin_try(spins, || self.try_write())
+    }
 }
 
 /// RAII structure used to release the exclusive write access of a lock when dropped.
@@ -91,15 +202,201 @@ impl<T: ?Sized> RwLock<T> {
 #[must_use = "if unused the RwLock will immediately unlock"]
 pub struct RwLockWriteGuard<'a, T: ?Sized> {
     pub(super) permits_acquired: u32,
+    /// Whether tasks waiting for a write have been notified explicitly.
+    pub(super) notified: bool,
     pub(super) lock: &'a RwLock<T>,
 }
 
 unsafe impl<T: ?Sized + Send + Sync> Send for RwLockWriteGuard<'_, T> {}
 unsafe impl<T: ?Sized + Send + Sync> Sync for RwLockWriteGuard<'_, T> {}
 
+impl<'a, T: ?Sized> RwLockWriteGuard<'a, T> {
+    /// Downgrades the write access to read access, letting other readers in.
+    ///
+    /// The lock is not released in between, so no writer can access the data before the returned
+    /// read guard is dropped. The write counts as completed: the [version](RwLock::version) is
+    /// advanced, and the tasks waiting in [`RwLock::wait_until_read`] are notified as if the
+    /// guard was dropped.
+    ///
+    /// This is an associated function that needs to be used as `RwLockWriteGuard::downgrade(...)`.
+    /// A method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockWriteGuard;
+    ///
+    /// let lock = RwLock::new(1);
+    /// let mut guard = lock.write().await;
+    /// *guard = 2;
+    ///
+    /// let guard = RwLockWriteGuard::downgrade(guard);
+    /// assert_eq!(*guard, 2);
+    /// assert_eq!(*lock.read().await, 2);
+    /// assert!(lock.try_write().is_none());
+    /// # }
+    /// ```
+    pub fn downgrade(this: Self) -> RwLockReadGuard<'a, T> {
+        let lock = this.lock;
+        let permits = this.permits_acquired;
+        let notified = this.notified;
+        std::mem::forget(this);
+        // keep one permit as a reader
+        lock.release_write(permits - 1, notified);
+        RwLockReadGuard { lock }
+    }
+
+    /// Makes a new [`MappedRwLockWriteGuard`] for a component of the locked data.
+    ///
+    /// This operation cannot fail as the [`RwLockWriteGuard`] passed in already locked the data.
+    /// The mapped guard releases the write access as this guard would, and tasks waiting in
+    /// [`RwLock::wait_until_read`] are notified unless this guard has notified them explicitly.
+    ///
+    /// This is an associated function that needs to be used as `RwLockWriteGuard::map(...)`. A
+    /// method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockWriteGuard;
+    ///
+    /// let lock = RwLock::new((0, vec![1, 2, 3]));
+    /// let mut tail = RwLockWriteGuard::map(lock.try_write().unwrap(), |v| &mut v.1[1..]);
+    /// tail.fill(0);
+    /// drop(tail);
+    ///
+    /// assert_eq!(*lock.try_read().unwrap(), (0, vec![1, 0, 0]));
+    /// ```
+    pub fn map<U, F>(mut this: Self, f: F) -> MappedRwLockWriteGuard<'a, U>
+    where
+        U: ?Sized,
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let d = f(&mut *this) as *mut U;
+        let lock = this.lock;
+        let permits_acquired = this.permits_acquired;
+        let notified = this.notified;
+        std::mem::forget(this);
+        MappedRwLockWriteGuard {
+            permits_acquired,
+            notified,
+            s: &lock.s,
+            w: &lock.w,
+            waiting: &lock.waiting,
+            version: &lock.version,
+            writing: &lock.writing,
+            d,
+        }
+    }
+
+    /// Attempts to make a new [`MappedRwLockWriteGuard`] for a component of the locked data.
+    ///
+    /// If the closure returns `None`, the original guard is returned back, so the write access is
+    /// kept.
+    ///
+    /// This is an associated function that needs to be used as `RwLockWriteGuard::filter_map(...)`.
+    /// A method would interfere with methods of the same name on the contents of the locked data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use mea::rwlock::RwLock;
+    /// use mea::rwlock::RwLockWriteGuard;
+    ///
+    /// let lock = RwLock::new(HashMap::from([("a", 1)]));
+    /// let guard = lock.try_write().unwrap();
+    /// let mut guard = RwLockWriteGuard::filter_map(guard, |m| m.get_mut("b")).unwrap_err();
+    /// guard.insert("b", 2);
+    /// let mut b = RwLockWriteGuard::filter_map(guard, |m| m.get_mut("b")).unwrap();
+    /// *b += 1;
+    /// drop(b);
+    ///
+    /// assert_eq!(lock.try_read().unwrap()["b"], 3);
+    /// ```
+    pub fn filter_map<U, F>(mut this: Self, f: F) -> Result<MappedRwLockWriteGuard<'a, U>, Self>
+    where
+        U: ?Sized,
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        match f(&mut *this) {
+            Some(d) => {
+                let d = d as *mut U;
+                let lock = this.lock;
+                let permits_acquired = this.permits_acquired;
+                let notified = this.notified;
+                std::mem::forget(this);
+                Ok(MappedRwLockWriteGuard {
+                    permits_acquired,
+                    notified,
+                    s: &lock.s,
+                    w: &lock.w,
+                    waiting: &lock.waiting,
+                    version: &lock.version,
+                    writing: &lock.writing,
+                    d,
+                })
+            }
+            None => Err(this),
+        }
+    }
+
+    /// Notifies one task waiting in [`RwLock::wait_until_read`] to re-check its condition.
+    ///
+    /// The notified task re-checks its condition once this write access is released. By default,
+    /// all waiting tasks are notified when the write guard is dropped; once this method or
+    /// [`notify_all_waiters`](RwLockWriteGuard::notify_all_waiters) is called, only the explicitly
+    /// notified tasks are.
+    ///
+    /// Returns `true` if a task was notified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::rwlock::RwLock;
+    ///
+    /// let lock = Arc::new(RwLock::new(Vec::new()));
+    /// let lock_clone = lock.clone();
+    ///
+    /// tokio::spawn(async move {
+    ///     let mut queue = lock_clone.write().await;
+    ///     queue.push(1);
+    ///     queue.notify_one_waiter();
+    /// });
+    ///
+    /// let queue = lock.wait_until_read(|queue| !queue.is_empty()).await;
+    /// assert_eq!(*queue, vec![1]);
+    /// # }
+    /// ```
+    pub fn notify_one_waiter(&mut self) -> bool {
+        self.notified = true;
+        self.lock.w.lock().notify_one()
+    }
+
+    /// Notifies all tasks waiting in [`RwLock::wait_until_read`] to re-check their condition.
+    ///
+    /// The notified tasks re-check their condition once this write access is released.
+    ///
+    /// Returns `true` if at least one task was notified.
+    pub fn notify_all_waiters(&mut self) -> bool {
+        self.notified = true;
+        self.lock.w.lock().notify_all()
+    }
+}
+
 impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
     fn drop(&mut self) {
-        self.lock.s.release(self.permits_acquired);
+        self.lock
+            .release_write(self.permits_acquired, self.notified);
     }
 }
 
@@ -0,0 +1,173 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::ready;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::internal;
+use crate::semaphore::Closed;
+use crate::semaphore::OwnedSemaphorePermit;
+use crate::semaphore::Semaphore;
+use crate::semaphore::SemaphorePermit;
+
+/// A future acquiring permits from a semaphore.
+///
+/// This type is created by the [`acquire`] method. Dropping it before it completes removes the
+/// request from the queue, and gives back any permits already assigned to it.
+///
+/// [`acquire`]: Semaphore::acquire
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Acquire<'a> {
+    sem: &'a Semaphore,
+    inner: internal::Acquire<&'a internal::Semaphore>,
+    permits: u32,
+    /// Taken when the future completes.
+    trace: Option<internal::AcquireTrace>,
+}
+
+impl<'a> Acquire<'a> {
+    pub(super) fn new(sem: &'a Semaphore, permits: u32) -> Self {
+        Self {
+            sem,
+            inner: internal::Acquire::new(&sem.s, permits),
+            permits,
+            trace: Some(internal::AcquireTrace::start("semaphore", &sem.s, permits)),
+        }
+    }
+}
+
+impl fmt::Debug for Acquire<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Acquire")
+            .field("sem", &self.sem)
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = SemaphorePermit<'a>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.sem.reclaim_expired_leases();
+        let Poll::Ready(acquired) = Pin::new(&mut this.inner).poll(cx) else {
+            this.sem.wake_at_lease_deadline(cx.waker());
+            return Poll::Pending;
+        };
+        let trace = this
+            .trace
+            .take()
+            .expect("`Acquire` polled after completion");
+        if acquired.is_err() {
+            panic!("Semaphore is closed");
+        }
+        trace.granted(&this.sem.s, this.permits);
+        Poll::Ready(SemaphorePermit {
+            sem: this.sem,
+            permits: this.permits,
+        })
+    }
+}
+
+/// A future acquiring permits from a semaphore wrapped in an [`Arc`].
+///
+/// This type is created by the [`acquire_owned`] method. Dropping it before it completes removes
+/// the request from the queue, and gives back any permits already assigned to it.
+///
+/// [`acquire_owned`]: Semaphore::acquire_owned
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AcquireOwned {
+    inner: internal::Acquire<OwnedSemaphore>,
+    permits: u32,
+    /// Taken when the future completes.
+    trace: Option<internal::AcquireTrace>,
+}
+
+/// An owning handle to the internal semaphore of a [`Semaphore`].
+#[derive(Debug)]
+struct OwnedSemaphore(Arc<Semaphore>);
+
+impl Deref for OwnedSemaphore {
+    type Target = internal::Semaphore;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0.s
+    }
+}
+
+impl AcquireOwned {
+    pub(super) fn new(sem: Arc<Semaphore>, permits: u32) -> Self {
+        let trace = internal::AcquireTrace::start("semaphore", &sem.s, permits);
+        Self {
+            inner: internal::Acquire::new(OwnedSemaphore(sem), permits),
+            permits,
+            trace: Some(trace),
+        }
+    }
+}
+
+impl fmt::Debug for AcquireOwned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcquireOwned")
+            .field("sem", &self.inner.semaphore().0)
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl AcquireOwned {
+    /// Polls for the permits, and returns `Err(Closed)` instead of panicking if the semaphore is
+    /// closed.
+    pub(super) fn poll_checked(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<OwnedSemaphorePermit, Closed>> {
+        self.inner.semaphore().0.reclaim_expired_leases();
+        let Poll::Ready(acquired) = Pin::new(&mut self.inner).poll(cx) else {
+            self.inner.semaphore().0.wake_at_lease_deadline(cx.waker());
+            return Poll::Pending;
+        };
+        let trace = self
+            .trace
+            .take()
+            .expect("`AcquireOwned` polled after completion");
+        if acquired.is_err() {
+            return Poll::Ready(Err(Closed));
+        }
+        let sem = self.inner.semaphore().0.clone();
+        trace.granted(&sem.s, self.permits);
+        Poll::Ready(Ok(OwnedSemaphorePermit {
+            sem,
+            permits: self.permits,
+        }))
+    }
+}
+
+impl Future for AcquireOwned {
+    type Output = OwnedSemaphorePermit;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match ready!(self.get_mut().poll_checked(cx)) {
+            Ok(permit) => Poll::Ready(permit),
+            Err(Closed) => panic!("Semaphore is closed"),
+        }
+    }
+}
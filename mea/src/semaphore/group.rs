@@ -0,0 +1,196 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::semaphore::OwnedSemaphorePermit;
+use crate::semaphore::Semaphore;
+use crate::semaphore::SemaphorePermit;
+
+/// A shared concurrency budget for a group of semaphores.
+///
+/// Each member created by [`semaphore`] has its own local limit, and every permit a member hands
+/// out is also drawn from the group's global pool. So the permits held across all members never
+/// exceed the global limit, and the permits held from one member never exceed its local limit.
+///
+/// Members always take their local permits before the global ones, and never wait for local
+/// permits while holding global ones, so acquiring from several members cannot deadlock.
+///
+/// # Examples
+///
+/// ```
+/// # #[tokio::main]
+/// # async fn main() {
+/// use mea::semaphore::SemaphoreGroup;
+///
+/// let group = SemaphoreGroup::new(3);
+/// let tenant_a = group.semaphore(2);
+/// let tenant_b = group.semaphore(2);
+///
+/// let a = tenant_a.acquire(2).await;
+/// let b = tenant_b.acquire(1).await;
+/// assert_eq!(group.available_permits(), 0);
+///
+/// // tenant_b still has local permits, but the global budget is exhausted
+/// assert!(tenant_b.try_acquire(1).is_none());
+///
+/// drop(a);
+/// assert_eq!(group.available_permits(), 2);
+/// assert!(tenant_b.try_acquire(1).is_some());
+/// # drop(b);
+/// # }
+/// ```
+///
+/// [`semaphore`]: SemaphoreGroup::semaphore
+#[derive(Debug)]
+pub struct SemaphoreGroup {
+    global: Arc<Semaphore>,
+}
+
+impl SemaphoreGroup {
+    /// Creates a new group whose members share `permits` global permits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::SemaphoreGroup;
+    ///
+    /// let group = SemaphoreGroup::new(10);
+    /// ```
+    pub fn new(permits: u32) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(permits)),
+        }
+    }
+
+    /// Creates a new member semaphore with `permits` local permits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::SemaphoreGroup;
+    ///
+    /// let group = SemaphoreGroup::new(10);
+    /// let member = group.semaphore(4);
+    /// assert_eq!(member.available_permits(), 4);
+    /// ```
+    pub fn semaphore(&self, permits: u32) -> GroupSemaphore {
+        GroupSemaphore {
+            local: Semaphore::new(permits),
+            global: self.global.clone(),
+        }
+    }
+
+    /// Returns the number of global permits currently available.
+    pub fn available_permits(&self) -> u32 {
+        self.global.available_permits()
+    }
+}
+
+/// A member semaphore of a [`SemaphoreGroup`].
+///
+/// This type is created by the [`SemaphoreGroup::semaphore`] method.
+#[derive(Debug)]
+pub struct GroupSemaphore {
+    local: Semaphore,
+    global: Arc<Semaphore>,
+}
+
+impl GroupSemaphore {
+    /// Returns the number of local permits currently available.
+    ///
+    /// Acquiring may still wait when local permits are available but the group's global permits
+    /// are not.
+    pub fn available_permits(&self) -> u32 {
+        self.local.available_permits()
+    }
+
+    /// Attempts to acquire `permits` permits from both this member and its group without waiting.
+    ///
+    /// Returns `None` if either the local or the global permits are not available, in which case
+    /// no permits are taken from either.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::SemaphoreGroup;
+    ///
+    /// let group = SemaphoreGroup::new(1);
+    /// let member = group.semaphore(2);
+    ///
+    /// let permit = member.try_acquire(1).unwrap();
+    /// assert!(member.try_acquire(1).is_none());
+    /// ```
+    pub fn try_acquire(&self, permits: u32) -> Option<GroupSemaphorePermit<'_>> {
+        let local = self.local.try_acquire(permits)?;
+        let global = self.global.clone().try_acquire_owned(permits)?;
+        Some(GroupSemaphorePermit { global, local })
+    }
+
+    /// Acquires `permits` permits from both this member and its group.
+    ///
+    /// The local permits are acquired first, and then the global permits. Returns a
+    /// [`GroupSemaphorePermit`] that releases both when dropped.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses queues to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire` makes you lose your place in the queues, and releases any
+    /// local permits already acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::SemaphoreGroup;
+    ///
+    /// let group = SemaphoreGroup::new(4);
+    /// let member = group.semaphore(2);
+    ///
+    /// let permit = member.acquire(2).await;
+    /// assert_eq!(member.available_permits(), 0);
+    /// assert_eq!(group.available_permits(), 2);
+    /// # drop(permit);
+    /// # }
+    /// ```
+    pub async fn acquire(&self, permits: u32) -> GroupSemaphorePermit<'_> {
+        let local = self.local.acquire(permits).await;
+        let global = self.global.clone().acquire_owned(permits).await;
+        GroupSemaphorePermit { global, local }
+    }
+}
+
+/// A permit from a [`GroupSemaphore`], holding both local and global permits.
+///
+/// This type is created by the [`acquire`] and [`try_acquire`] methods on [`GroupSemaphore`].
+/// When the permit is dropped, the permits are returned to both the member and its group.
+///
+/// [`acquire`]: GroupSemaphore::acquire
+/// [`try_acquire`]: GroupSemaphore::try_acquire
+#[must_use = "permits are released immediately when dropped"]
+#[derive(Debug)]
+pub struct GroupSemaphorePermit<'a> {
+    // release the global permits first so that other members can make progress
+    global: OwnedSemaphorePermit,
+    local: SemaphorePermit<'a>,
+}
+
+impl GroupSemaphorePermit<'_> {
+    /// Returns the number of permits this permit holds.
+    pub fn permits(&self) -> u32 {
+        debug_assert_eq!(self.local.permits(), self.global.permits());
+        self.local.permits()
+    }
+}
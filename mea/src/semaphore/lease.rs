@@ -0,0 +1,256 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::poll_fn;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Waker;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::internal;
+use crate::internal::Mutex;
+use crate::semaphore::Semaphore;
+
+/// The leases handed out by a semaphore, see [`Semaphore::acquire_lease`].
+#[derive(Debug)]
+pub(super) struct Leases {
+    /// The number of outstanding leases, to skip the lock when there are none.
+    len: AtomicUsize,
+    leases: Mutex<Vec<Arc<Lease>>>,
+}
+
+#[derive(Debug)]
+struct Lease {
+    permits: u32,
+    ttl: Duration,
+    deadline: Mutex<Instant>,
+    /// Set by whoever returns the permits to the semaphore, either an expiry check or the drop
+    /// of the lease permit, so that they are returned exactly once.
+    reclaimed: AtomicBool,
+}
+
+impl Leases {
+    pub(super) const fn new() -> Self {
+        Self {
+            len: AtomicUsize::new(0),
+            leases: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Removes the expired leases, and returns the number of permits they held.
+    pub(super) fn reclaim_expired(&self) -> u32 {
+        if self.len.load(Ordering::Acquire) == 0 {
+            return 0;
+        }
+
+        let now = Instant::now();
+        let mut reclaimed = 0;
+        let mut leases = self.leases.lock();
+        leases.retain(|lease| {
+            let deadline = lease.deadline.lock();
+            if *deadline <= now && !lease.reclaimed.swap(true, Ordering::AcqRel) {
+                reclaimed += lease.permits;
+            }
+            !lease.reclaimed.load(Ordering::Acquire)
+        });
+        self.len.store(leases.len(), Ordering::Release);
+        reclaimed
+    }
+
+    /// Returns the earliest deadline of the outstanding leases, if any.
+    fn next_deadline(&self) -> Option<Instant> {
+        if self.len.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+
+        let leases = self.leases.lock();
+        leases
+            .iter()
+            .filter(|lease| !lease.reclaimed.load(Ordering::Acquire))
+            .map(|lease| *lease.deadline.lock())
+            .min()
+    }
+
+    fn remove(&self, lease: &Arc<Lease>) {
+        let mut leases = self.leases.lock();
+        leases.retain(|l| !Arc::ptr_eq(l, lease));
+        self.len.store(leases.len(), Ordering::Release);
+    }
+}
+
+impl Semaphore {
+    /// Acquires `n` permits from the semaphore as a lease that expires after `ttl` unless
+    /// renewed.
+    ///
+    /// A lease protects the semaphore against holders that stall or leak their permits: once a
+    /// lease is past its deadline, its permits are reclaimed the next time a caller acquires
+    /// permits from this semaphore, and can be handed out again. The holder can push the deadline
+    /// back with [`LeasePermit::renew`].
+    ///
+    /// Expired leases are reclaimed by calls to `try_acquire*`, and by every poll of a pending
+    /// `acquire*`. Tasks waiting for permits are also woken at the earliest lease deadline, by a
+    /// timer thread that the crate spawns on first use and shares across all semaphores, so that
+    /// a lease expiring while every acquirer is already parked does not leave them waiting.
+    ///
+    /// Dropping a lease returns its permits to the semaphore, unless they have been reclaimed
+    /// already.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire_lease` makes you lose your place in the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the semaphore is [closed](Semaphore::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// let lease = sem.acquire_lease(1, Duration::from_millis(10)).await;
+    /// assert!(sem.try_acquire(1).is_none());
+    ///
+    /// // the holder stalls without renewing the lease
+    /// tokio::time::sleep(Duration::from_millis(20)).await;
+    /// assert!(sem.try_acquire(1).is_some());
+    /// assert!(lease.is_reclaimed());
+    /// # }
+    /// ```
+    pub async fn acquire_lease(&self, permits: u32, ttl: Duration) -> LeasePermit<'_> {
+        self.reclaiming(self.s.acquire(permits))
+            .await
+            .expect("Semaphore is closed");
+
+        let lease = Arc::new(Lease {
+            permits,
+            ttl,
+            deadline: Mutex::new(Instant::now() + ttl),
+            reclaimed: AtomicBool::new(false),
+        });
+        let mut leases = self.leases.leases.lock();
+        leases.push(lease.clone());
+        self.leases.len.store(leases.len(), Ordering::Release);
+        drop(leases);
+        // the tasks parked before this lease existed have no wakeup at its deadline yet
+        self.s.wake_waiters();
+
+        LeasePermit { sem: self, lease }
+    }
+
+    /// Returns the permits of the expired leases to the semaphore.
+    pub(super) fn reclaim_expired_leases(&self) {
+        let reclaimed = self.leases.reclaim_expired();
+        self.s.release(reclaimed);
+    }
+
+    /// Wakes `waker` at the earliest deadline of the outstanding leases, so that a pending
+    /// acquisition polls again and reclaims the lease once it expires.
+    pub(super) fn wake_at_lease_deadline(&self, waker: &Waker) {
+        if let Some(deadline) = self.leases.next_deadline() {
+            internal::wake_at(deadline, waker);
+        }
+    }
+
+    /// Drives `acquire` to completion, reclaiming the expired leases before each poll.
+    pub(super) async fn reclaiming<F: Future>(&self, acquire: F) -> F::Output {
+        let mut acquire = pin!(acquire);
+        poll_fn(|cx: &mut Context<'_>| {
+            self.reclaim_expired_leases();
+            let polled = acquire.as_mut().poll(cx);
+            if polled.is_pending() {
+                self.wake_at_lease_deadline(cx.waker());
+            }
+            polled
+        })
+        .await
+    }
+}
+
+/// A permit from the semaphore that expires unless renewed.
+///
+/// This type is created by the [`acquire_lease`] method. When the lease is dropped, its permits
+/// are returned to the semaphore, unless they have already been reclaimed on expiry.
+///
+/// [`acquire_lease`]: Semaphore::acquire_lease
+#[must_use = "permits are released immediately when dropped"]
+#[derive(Debug)]
+pub struct LeasePermit<'a> {
+    sem: &'a Semaphore,
+    lease: Arc<Lease>,
+}
+
+impl LeasePermit<'_> {
+    /// Resets the deadline of the lease to `ttl` from now.
+    ///
+    /// Returns `false` if the lease has already been reclaimed, in which case the permits are no
+    /// longer held and the lease cannot be revived.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// let lease = sem.acquire_lease(1, Duration::from_secs(60)).await;
+    /// assert!(lease.renew());
+    /// # }
+    /// ```
+    pub fn renew(&self) -> bool {
+        let mut deadline = self.lease.deadline.lock();
+        if self.lease.reclaimed.load(Ordering::Acquire) {
+            return false;
+        }
+        *deadline = Instant::now() + self.lease.ttl;
+        true
+    }
+
+    /// Returns `true` if the lease has been reclaimed, so that its permits are no longer held.
+    ///
+    /// A lease past its deadline is not reclaimed, and this method keeps returning `false`,
+    /// until an acquisition on the semaphore reclaims it, see [`Semaphore::acquire_lease`].
+    pub fn is_reclaimed(&self) -> bool {
+        self.lease.reclaimed.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of permits this lease holds, or held before it was reclaimed.
+    pub fn permits(&self) -> u32 {
+        self.lease.permits
+    }
+}
+
+impl Drop for LeasePermit<'_> {
+    fn drop(&mut self) {
+        if !self.lease.reclaimed.swap(true, Ordering::AcqRel) {
+            self.sem.leases.remove(&self.lease);
+            self.sem.s.release(self.lease.permits);
+        }
+    }
+}
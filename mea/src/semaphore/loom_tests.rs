@@ -0,0 +1,90 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Model checked tests of the public semaphore API, see `internal::sync`.
+//!
+//! These tests are not part of the default test run. Run them with:
+//!
+//! ```shell
+//! LOOM_MAX_PREEMPTIONS=2 RUSTFLAGS="--cfg loom" cargo test -p mea --release --lib loom_tests
+//! ```
+
+use loom::cell::UnsafeCell;
+use loom::future::block_on;
+use loom::sync::Arc;
+use loom::thread;
+
+use crate::semaphore::Semaphore;
+
+#[test]
+fn acquire_is_exclusive() {
+    loom::model(|| {
+        let sem = Arc::new(Semaphore::new(1));
+        let cell = Arc::new(UnsafeCell::new(0));
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let sem = sem.clone();
+                let cell = cell.clone();
+                thread::spawn(move || {
+                    block_on(async {
+                        let _permit = sem.acquire(1).await;
+                        cell.with_mut(|n| unsafe { *n += 1 });
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(cell.with(|n| unsafe { *n }), 2);
+        assert_eq!(sem.available_permits(), 1);
+    });
+}
+
+#[test]
+fn release_wakes_waiter_for_multiple_permits() {
+    loom::model(|| {
+        let sem = Arc::new(Semaphore::new(0));
+
+        let waiter = {
+            let sem = sem.clone();
+            thread::spawn(move || block_on(sem.acquire(2)).forget())
+        };
+        sem.release(1);
+        sem.release(1);
+        waiter.join().unwrap();
+
+        assert_eq!(sem.available_permits(), 0);
+    });
+}
+
+#[test]
+fn try_acquire_and_release() {
+    loom::model(|| {
+        let sem = Arc::new(Semaphore::new(1));
+
+        let other = {
+            let sem = sem.clone();
+            thread::spawn(move || sem.try_acquire(1).is_some())
+        };
+        let acquired = sem.try_acquire(1).map(|p| p.permits()).unwrap_or(0);
+        let other_acquired = other.join().unwrap();
+
+        // both may succeed if the first permit is released before the other try
+        assert!(acquired == 1 || other_acquired);
+        assert_eq!(sem.available_permits(), 1);
+    });
+}
@@ -61,11 +61,10 @@
 //! use std::fs::File;
 //! use std::io::Result;
 //! use std::io::Write;
-//! use std::sync::LazyLock;
 //!
 //! use mea::semaphore::Semaphore;
 //!
-//! static PERMITS: LazyLock<Semaphore> = LazyLock::new(|| Semaphore::new(100));
+//! static PERMITS: Semaphore = Semaphore::new(100);
 //!
 //! async fn write_to_file(message: &[u8]) -> Result<()> {
 //!     let _permit = PERMITS.acquire(1).await;
@@ -78,10 +77,39 @@
 //! [`acquire`]: Semaphore::acquire
 //! [`release`]: Semaphore::release
 
+use std::fmt;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::task::Poll;
 
 use crate::internal;
 
+mod acquire;
+pub use acquire::Acquire;
+pub use acquire::AcquireOwned;
+mod group;
+pub use group::GroupSemaphore;
+pub use group::GroupSemaphorePermit;
+pub use group::SemaphoreGroup;
+mod lease;
+pub use lease::LeasePermit;
+mod stream;
+pub use stream::PermitStream;
+mod watch;
+pub use watch::AvailabilityStream;
+mod weak;
+pub use weak::WeakSemaphore;
+mod wfq;
+pub use wfq::WfqSemaphore;
+pub use wfq::WfqSemaphorePermit;
+
+#[cfg(all(test, loom))]
+mod loom_tests;
+#[cfg(test)]
+mod prop_tests;
 #[cfg(test)]
 mod tests;
 
@@ -91,24 +119,109 @@ mod tests;
 #[derive(Debug)]
 pub struct Semaphore {
     s: internal::Semaphore,
+    leases: lease::Leases,
+    /// The maximum number of permits of a capped semaphore, see [`Semaphore::new_capped`].
+    cap: Option<u32>,
+    /// The number of permits owned by a capped semaphore, either available or acquired.
+    total: AtomicU32,
 }
 
 impl Semaphore {
-    /// Creates a new semaphore with the given number of permits.
+    /// The maximum number of available permits of a semaphore.
+    ///
+    /// Releasing permits beyond this number panics in [`release`], and fails in [`try_release`].
+    ///
+    /// [`release`]: Semaphore::release
+    /// [`try_release`]: Semaphore::try_release
+    pub const MAX_PERMITS: u32 = internal::Semaphore::MAX_PERMITS;
+
+    internal::sync::const_fn! {
+        /// Creates a new semaphore with the given number of permits.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use mea::semaphore::Semaphore;
+        ///
+        /// let sem = Semaphore::new(5); // Creates a semaphore with 5 permits
+        ///
+        /// // the constructor is `const`, so a semaphore can be a static item
+        /// static PERMITS: Semaphore = Semaphore::new(100);
+        /// ```
+        pub fn new(permits: u32) -> Self {
+            Self::with_policy(permits, Policy::Fifo)
+        }
+    }
+
+    internal::sync::const_fn! {
+        /// Creates a new semaphore with the given number of permits, which hands out permits to
+        /// waiting tasks in the order of the given [`Policy`].
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use mea::semaphore::Policy;
+        /// use mea::semaphore::Semaphore;
+        ///
+        /// // hand out permits to the most recent waiter first
+        /// let sem = Semaphore::with_policy(4, Policy::Lifo);
+        /// ```
+        pub fn with_policy(permits: u32, policy: Policy) -> Self {
+            Self {
+                s: internal::Semaphore::with_order(permits, matches!(policy, Policy::Lifo)),
+                leases: lease::Leases::new(),
+                cap: None,
+                total: AtomicU32::new(permits),
+            }
+        }
+    }
+
+    /// Creates a new semaphore with the given number of permits, which can never grow beyond
+    /// that number.
+    ///
+    /// This suits a fixed pool of resources, where an accidental over-release would silently
+    /// break the intended limit. Permits removed with [`forget`] can be added back with
+    /// [`release`], but only up to the initial number. A [`release`] past the cap is clamped,
+    /// and panics in debug builds, while [`try_release`] fails without adding any permits.
     ///
     /// # Examples
     ///
     /// ```
     /// use mea::semaphore::Semaphore;
     ///
-    /// let sem = Semaphore::new(5); // Creates a semaphore with 5 permits
+    /// let sem = Semaphore::new_capped(2);
+    /// assert_eq!(sem.forget(1), 1);
+    /// sem.release(1); // adds the forgotten permit back
+    /// assert_eq!(sem.available_permits(), 2);
     /// ```
-    pub fn new(permits: u32) -> Self {
+    ///
+    /// [`forget`]: Semaphore::forget
+    /// [`release`]: Semaphore::release
+    /// [`try_release`]: Semaphore::try_release
+    pub fn new_capped(permits: u32) -> Self {
         Self {
-            s: internal::Semaphore::new(permits),
+            cap: Some(permits),
+            ..Self::new(permits)
         }
     }
 
+    /// Returns the maximum number of permits this semaphore can hold.
+    ///
+    /// This is the cap of a [capped](Semaphore::new_capped) semaphore, and
+    /// [`MAX_PERMITS`](Semaphore::MAX_PERMITS) otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// assert_eq!(Semaphore::new(2).max_permits(), Semaphore::MAX_PERMITS);
+    /// assert_eq!(Semaphore::new_capped(2).max_permits(), 2);
+    /// ```
+    pub fn max_permits(&self) -> u32 {
+        self.cap.unwrap_or(Self::MAX_PERMITS)
+    }
+
     /// Returns the current number of permits available.
     ///
     /// # Examples
@@ -126,6 +239,80 @@ impl Semaphore {
         self.s.available_permits()
     }
 
+    /// Returns the number of tasks currently waiting for permits.
+    ///
+    /// Together with [`available_permits`], this tells how oversubscribed the semaphore is. The
+    /// number is read without taking a lock, so it may be slightly stale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(1));
+    /// let permit = sem.acquire(1).await;
+    /// assert_eq!(sem.waiters(), 0);
+    ///
+    /// let waiter = tokio::spawn(sem.clone().acquire_owned(1));
+    /// while sem.waiters() == 0 {
+    ///     tokio::task::yield_now().await;
+    /// }
+    ///
+    /// drop(permit);
+    /// waiter.await.unwrap();
+    /// assert_eq!(sem.waiters(), 0);
+    /// # }
+    /// ```
+    ///
+    /// [`available_permits`]: Semaphore::available_permits
+    pub fn waiters(&self) -> usize {
+        self.s.queued()
+    }
+
+    /// Closes the semaphore.
+    ///
+    /// All tasks waiting for permits are woken up: [`acquire_checked`] and
+    /// [`acquire_owned_checked`] return `Err(Closed)`, and so do subsequent calls. Permits that
+    /// are already acquired remain valid and are released as usual when dropped.
+    /// [`try_acquire`] returns `None` once the semaphore is closed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::Closed;
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// let permit = sem.acquire(1).await;
+    ///
+    /// sem.close();
+    /// assert!(sem.is_closed());
+    /// assert_eq!(sem.acquire_checked(1).await.unwrap_err(), Closed);
+    ///
+    /// drop(permit);
+    /// assert_eq!(sem.available_permits(), 1);
+    /// assert!(sem.try_acquire(1).is_none());
+    /// # }
+    /// ```
+    ///
+    /// [`acquire_checked`]: Semaphore::acquire_checked
+    /// [`acquire_owned_checked`]: Semaphore::acquire_owned_checked
+    /// [`try_acquire`]: Semaphore::try_acquire
+    pub fn close(&self) {
+        self.s.close();
+    }
+
+    /// Returns `true` if the semaphore is [closed](Semaphore::close).
+    pub fn is_closed(&self) -> bool {
+        self.s.is_closed()
+    }
+
     /// Reduces the semaphore's permits by a maximum of `n`.
     ///
     /// Returns the actual number of permits that were reduced. This may be less
@@ -147,14 +334,22 @@ impl Semaphore {
     /// assert_eq!(sem.available_permits(), 0);
     /// ```
     pub fn forget(&self, n: u32) -> u32 {
-        self.s.forget(n)
+        let forgotten = self.s.forget(n);
+        self.shrink(forgotten);
+        forgotten
     }
 
     /// Adds `n` new permits to the semaphore.
     ///
+    /// For a [capped](Semaphore::new_capped) semaphore, the permits beyond the cap are discarded.
+    ///
     /// # Panics
     ///
-    /// Panics if adding the permits would cause the total number of permits to overflow.
+    /// Panics if adding the permits would cause the number of available permits to exceed
+    /// [`MAX_PERMITS`](Semaphore::MAX_PERMITS). Use [`Semaphore::try_release`] to handle that
+    /// case.
+    ///
+    /// In debug builds, panics if adding the permits would exceed the cap of a capped semaphore.
     ///
     /// # Examples
     ///
@@ -166,14 +361,112 @@ impl Semaphore {
     /// assert_eq!(sem.available_permits(), 2);
     /// ```
     pub fn release(&self, permits: u32) {
-        self.s.release(permits);
+        let granted = self.grow(permits);
+        debug_assert_eq!(
+            granted, permits,
+            "releasing {permits} permits would exceed the cap of the semaphore ({:?})",
+            self.cap
+        );
+        self.add_permits(granted)
+            .expect("failed to release permits");
+    }
+
+    /// Adds `n` new permits to the semaphore, or returns an error if the number of available
+    /// permits would exceed [`MAX_PERMITS`](Semaphore::MAX_PERMITS), or the cap of a
+    /// [capped](Semaphore::new_capped) semaphore.
+    ///
+    /// On error, no permits are added. The error carries the maximum number of permits of the
+    /// semaphore, so that callers can clamp the number of permits to release.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(Semaphore::MAX_PERMITS - 1);
+    /// assert!(sem.try_release(1).is_ok());
+    ///
+    /// let err = sem.try_release(1).unwrap_err();
+    /// assert_eq!(err.max_permits(), Semaphore::MAX_PERMITS);
+    /// assert_eq!(sem.available_permits(), Semaphore::MAX_PERMITS);
+    /// ```
+    pub fn try_release(&self, permits: u32) -> Result<(), ReleaseOverflow> {
+        if !self.try_grow(permits) {
+            return Err(ReleaseOverflow {
+                max_permits: self.max_permits(),
+            });
+        }
+        self.add_permits(permits)
+    }
+
+    /// Adds `n` permits to the internal semaphore, which are already accounted for under the cap.
+    fn add_permits(&self, n: u32) -> Result<(), ReleaseOverflow> {
+        // the permits of a capped semaphore never overflow, so the accounting needs no rollback
+        if self.s.try_release(n) {
+            Ok(())
+        } else {
+            Err(ReleaseOverflow {
+                max_permits: Self::MAX_PERMITS,
+            })
+        }
+    }
+
+    /// Accounts for `n` new permits, and returns the number of them that fits under the cap.
+    fn grow(&self, n: u32) -> u32 {
+        let Some(cap) = self.cap else {
+            return n;
+        };
+        let mut total = self.total.load(Ordering::Acquire);
+        loop {
+            let granted = n.min(cap - total);
+            match self.total.compare_exchange(
+                total,
+                total + granted,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return granted,
+                Err(actual) => total = actual,
+            }
+        }
+    }
+
+    /// Accounts for `n` new permits if all of them fit under the cap, and returns whether they do.
+    fn try_grow(&self, n: u32) -> bool {
+        let Some(cap) = self.cap else {
+            return true;
+        };
+        let mut total = self.total.load(Ordering::Acquire);
+        loop {
+            if n > cap - total {
+                return false;
+            }
+            match self
+                .total
+                .compare_exchange(total, total + n, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return true,
+                Err(actual) => total = actual,
+            }
+        }
+    }
+
+    /// Accounts for `n` permits removed from the semaphore.
+    fn shrink(&self, n: u32) {
+        if self.cap.is_some() {
+            self.total.fetch_sub(n, Ordering::AcqRel);
+        }
     }
 
     /// Attempts to acquire `n` permits from the semaphore without blocking.
     ///
     /// If the permits are successfully acquired, a [`SemaphorePermit`] is returned.
     /// The permits will be automatically returned to the semaphore when the permit
-    /// is dropped, unless [`forget`] is called.
+    /// is dropped, unless [`forget`] is called. Returns `None` if the semaphore is
+    /// [closed](Semaphore::close).
+    ///
+    /// Acquiring zero permits always succeeds, even if the semaphore is closed, and returns a
+    /// permit whose drop is a no-op.
     ///
     /// # Examples
     ///
@@ -196,20 +489,122 @@ impl Semaphore {
     ///
     /// [`forget`]: SemaphorePermit::forget
     pub fn try_acquire(&self, permits: u32) -> Option<SemaphorePermit<'_>> {
-        self.s
-            .try_acquire(permits)
-            .then_some(SemaphorePermit { sem: self, permits })
+        self.try_acquire_checked(permits).ok()
+    }
+
+    /// Attempts to acquire `n` permits from the semaphore without blocking, and tells why if it
+    /// fails.
+    ///
+    /// This method behaves like [`Semaphore::try_acquire`], except that it distinguishes a
+    /// [closed](Semaphore::close) semaphore, where retrying is pointless, from one that is
+    /// momentarily out of permits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::Semaphore;
+    /// use mea::semaphore::TryAcquireError;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// let permit = sem.try_acquire_checked(1).unwrap();
+    /// assert_eq!(
+    ///     sem.try_acquire_checked(1).unwrap_err(),
+    ///     TryAcquireError::NoPermits
+    /// );
+    ///
+    /// sem.close();
+    /// assert_eq!(
+    ///     sem.try_acquire_checked(1).unwrap_err(),
+    ///     TryAcquireError::Closed
+    /// );
+    /// ```
+    pub fn try_acquire_checked(
+        &self,
+        permits: u32,
+    ) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.reclaim_expired_leases();
+        if self.s.try_acquire(permits) {
+            Ok(SemaphorePermit { sem: self, permits })
+        } else if self.s.is_closed() {
+            Err(TryAcquireError::Closed)
+        } else {
+            Err(TryAcquireError::NoPermits)
+        }
+    }
+
+    /// Acquires all the currently available permits without blocking.
+    ///
+    /// The permits are taken in a single atomic step, so a concurrent release is either included
+    /// in the returned permit or left available. The returned permit may hold zero permits, e.g.,
+    /// when the semaphore is [closed](Semaphore::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(5);
+    /// let in_flight = sem.try_acquire(2).unwrap();
+    ///
+    /// let drained = sem.acquire_all();
+    /// assert_eq!(drained.permits(), 3);
+    /// assert_eq!(sem.available_permits(), 0);
+    ///
+    /// drop(in_flight);
+    /// assert_eq!(sem.acquire_all().permits(), 2);
+    /// ```
+    pub fn acquire_all(&self) -> SemaphorePermit<'_> {
+        self.reclaim_expired_leases();
+        let permits = self.s.try_acquire_all();
+        SemaphorePermit { sem: self, permits }
+    }
+
+    /// Attempts to acquire `permits` permits, retrying up to `spins` times before giving up.
+    ///
+    /// Unlike [`acquire`], this method never enqueues the caller. It is meant for permits that
+    /// are held only briefly, where a holder is likely to release them within a short spin.
+    ///
+    /// The `spin` benchmark (`cargo bench -p mea --bench spin`) compares the spin variants of the
+    /// primitives with their single-shot tries, with 4 threads holding for short critical
+    /// sections. Run on a single-core machine, 256 spins raised the share of successful attempts
+    /// from about two fifths to four fifths, while each attempt took about twice as long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// let permit = sem.try_acquire_spin(1, 16).unwrap();
+    /// assert!(sem.try_acquire_spin(1, 16).is_none());
+    /// ```
+    ///
+    /// [`acquire`]: Semaphore::acquire
+    pub fn try_acquire_spin(&self, permits: u32, spins: u32) -> Option<SemaphorePermit<'_>> {
+        internal::spin_try(spins, || self.try_acquire(permits))
     }
 
     /// Acquires `n` permits from the semaphore.
     ///
     /// If the permits are not immediately available, this method will wait until they become
-    /// available. Returns a [`SemaphorePermit`] that will release the permits when dropped.
+    /// available. The returned [`Acquire`] future resolves to a [`SemaphorePermit`] that will
+    /// release the permits when dropped. The future is a named type, so it can be stored without
+    /// boxing.
+    ///
+    /// Acquiring zero permits completes immediately, even if other tasks are waiting or the
+    /// semaphore is closed, and returns a permit whose drop is a no-op.
     ///
     /// # Cancel safety
     ///
     /// This method uses a queue to fairly distribute permits in the order they were requested.
-    /// Cancelling a call to `acquire` makes you lose your place in the queue.
+    /// Cancelling a call to `acquire` makes you lose your place in the queue, and gives back any
+    /// permits already assigned to it. So giving up after a timeout by racing `acquire` against
+    /// a timer of your runtime, e.g., with `tokio::time::timeout`, never leaks permits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the semaphore is [closed](Semaphore::close). Use [`Semaphore::acquire_checked`]
+    /// to handle that case.
     ///
     /// # Examples
     ///
@@ -236,9 +631,246 @@ impl Semaphore {
     /// handle.await.unwrap();
     /// # }
     /// ```
-    pub async fn acquire(&self, permits: u32) -> SemaphorePermit<'_> {
-        self.s.acquire(permits).await;
-        SemaphorePermit { sem: self, permits }
+    pub fn acquire(&self, permits: u32) -> Acquire<'_> {
+        Acquire::new(self, permits)
+    }
+
+    /// Acquires `n` permits from the semaphore, or returns an error if the semaphore is closed.
+    ///
+    /// This method behaves like [`Semaphore::acquire`], except that it returns `Err(Closed)`
+    /// instead of panicking if the semaphore is [closed](Semaphore::close) before the permits are
+    /// acquired.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire_checked` makes you lose your place in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::Closed;
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// assert!(sem.acquire_checked(1).await.is_ok());
+    ///
+    /// sem.close();
+    /// assert_eq!(sem.acquire_checked(1).await.unwrap_err(), Closed);
+    /// # }
+    /// ```
+    pub async fn acquire_checked(&self, permits: u32) -> Result<SemaphorePermit<'_>, Closed> {
+        let trace = internal::AcquireTrace::start("semaphore", &self.s, permits);
+        self.reclaiming(self.s.acquire(permits))
+            .await
+            .map_err(|_| Closed)?;
+        trace.granted(&self.s, permits);
+        Ok(SemaphorePermit { sem: self, permits })
+    }
+
+    /// Acquires `n` permits from the semaphore, and passes the number of permits granted along
+    /// with the permit to `f`.
+    ///
+    /// This is a lower-level form of [`acquire`] for callers that act on the granted count right
+    /// away, e.g., to record metrics, before deciding what to do with the permit. Return the
+    /// permit from `f` to keep holding it; dropping it inside `f` releases the permits.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire_with` makes you lose your place in the queue, and `f` is
+    /// never called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the semaphore is [closed](Semaphore::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::atomic::AtomicU32;
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(8);
+    /// let in_flight = AtomicU32::new(0);
+    ///
+    /// let permit = sem
+    ///     .acquire_with(3, |granted, permit| {
+    ///         in_flight.fetch_add(granted, Ordering::Relaxed);
+    ///         permit
+    ///     })
+    ///     .await;
+    /// assert_eq!(in_flight.load(Ordering::Relaxed), 3);
+    /// assert_eq!(sem.available_permits(), 5);
+    /// # drop(permit);
+    /// # }
+    /// ```
+    ///
+    /// [`acquire`]: Semaphore::acquire
+    pub async fn acquire_with<'a, F, R>(&'a self, permits: u32, f: F) -> R
+    where
+        F: FnOnce(u32, SemaphorePermit<'a>) -> R,
+    {
+        let permit = self.acquire(permits).await;
+        f(permit.permits, permit)
+    }
+
+    /// Acquires `n` permits from the semaphore, yielding to the executor even if the permits are
+    /// immediately available.
+    ///
+    /// On a single-threaded executor, a task that acquires and releases in a tight loop never
+    /// yields as long as the permits are available, and starves the other tasks. This method
+    /// awaits `yield_now` once after taking immediately available permits, so other tasks get to
+    /// run. If the permits are not immediately available, the task waits as in [`acquire`], and
+    /// `yield_now` is not awaited since waiting already yields.
+    ///
+    /// `yield_now` is provided by the caller to stay runtime-agnostic, e.g.,
+    /// `tokio::task::yield_now()`.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire_cooperative` makes you lose your place in the queue, and
+    /// releases the permits if it is cancelled while yielding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// for _ in 0..100 {
+    ///     let permit = sem.acquire_cooperative(1, tokio::task::yield_now()).await;
+    ///     // Do some short work with the permit.
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`acquire`]: Semaphore::acquire
+    pub async fn acquire_cooperative<Y>(&self, permits: u32, yield_now: Y) -> SemaphorePermit<'_>
+    where
+        Y: Future<Output = ()>,
+    {
+        match self.try_acquire(permits) {
+            Some(permit) => {
+                yield_now.await;
+                permit
+            }
+            None => self.acquire(permits).await,
+        }
+    }
+
+    /// Acquires `n` permits from the semaphore, or gives up when `scope` is cancelled.
+    ///
+    /// If the permits are not immediately available, this method will wait until they become
+    /// available or the scope is cancelled, whichever happens first. When the scope is cancelled,
+    /// the pending request is removed from the queue, any permits assigned to it are returned to
+    /// the semaphore, and [`Cancelled`] is returned. If both happen at once, the permits win.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire_scoped` makes you lose your place in the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the semaphore is [closed](Semaphore::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::Semaphore;
+    /// use mea::signal::Signal;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// let scope = Signal::new();
+    ///
+    /// let permit = sem.acquire_scoped(1, &scope).await.unwrap();
+    /// scope.notify();
+    /// assert!(sem.acquire_scoped(1, &scope).await.is_err());
+    /// drop(permit);
+    /// assert_eq!(sem.available_permits(), 1);
+    /// # }
+    /// ```
+    pub async fn acquire_scoped<C: Cancellation>(
+        &self,
+        permits: u32,
+        scope: &C,
+    ) -> Result<SemaphorePermit<'_>, Cancelled> {
+        let mut acquire = pin!(self.reclaiming(self.s.acquire(permits)));
+        let mut cancelled = pin!(scope.cancelled());
+        std::future::poll_fn(|cx| {
+            if let Poll::Ready(acquired) = acquire.as_mut().poll(cx) {
+                acquired.expect("Semaphore is closed");
+                Poll::Ready(Ok(()))
+            } else if cancelled.as_mut().poll(cx).is_ready() {
+                Poll::Ready(Err(Cancelled))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await?;
+        Ok(SemaphorePermit { sem: self, permits })
+    }
+
+    /// Acquires `n` permits from the semaphore, or rejects the request if `max_queue` tasks are
+    /// already waiting for permits.
+    ///
+    /// This sheds load at the semaphore rather than letting the wait queue grow without bound: if
+    /// the permits are not immediately available and at least `max_queue` tasks are queued,
+    /// [`Rejected`] is returned at once. Otherwise, this method waits for the permits like
+    /// [`acquire`]. The queue length is checked atomically with enqueueing, so concurrent
+    /// acquirers cannot both slip past the limit.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire_bounded_queue` makes you lose your place in the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the semaphore is [closed](Semaphore::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::Rejected;
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// let permit = sem.acquire_bounded_queue(1, 0).await.unwrap();
+    ///
+    /// // the semaphore is saturated, and no task may wait
+    /// assert_eq!(sem.acquire_bounded_queue(1, 0).await.unwrap_err(), Rejected);
+    /// # }
+    /// ```
+    ///
+    /// [`acquire`]: Semaphore::acquire
+    pub async fn acquire_bounded_queue(
+        &self,
+        permits: u32,
+        max_queue: usize,
+    ) -> Result<SemaphorePermit<'_>, Rejected> {
+        match self
+            .reclaiming(self.s.acquire_bounded(permits, max_queue))
+            .await
+        {
+            Ok(()) => Ok(SemaphorePermit { sem: self, permits }),
+            Err(internal::AcquireError::QueueFull) => Err(Rejected),
+            Err(internal::AcquireError::Closed) => panic!("Semaphore is closed"),
+        }
     }
 
     /// Attempts to acquire `n` permits from the semaphore without blocking.
@@ -270,9 +902,10 @@ impl Semaphore {
     ///
     /// [`forget`]: SemaphorePermit::forget
     pub fn try_acquire_owned(self: Arc<Self>, permits: u32) -> Option<OwnedSemaphorePermit> {
+        self.reclaim_expired_leases();
         self.s
             .try_acquire(permits)
-            .then_some(OwnedSemaphorePermit { sem: self, permits })
+            .then(|| OwnedSemaphorePermit { sem: self, permits })
     }
 
     /// Acquires `n` permits from the semaphore.
@@ -280,13 +913,19 @@ impl Semaphore {
     /// The semaphore must be wrapped in an [`Arc`] to call this method.
     ///
     /// If the permits are not immediately available, this method will wait until they become
-    /// available. Returns a [`OwnedSemaphorePermit`] that will release the permits when dropped.
+    /// available. The returned [`AcquireOwned`] future resolves to an [`OwnedSemaphorePermit`]
+    /// that will release the permits when dropped.
     ///
     /// # Cancel safety
     ///
     /// This method uses a queue to fairly distribute permits in the order they were requested.
     /// Cancelling a call to `acquire_owned` makes you lose your place in the queue.
     ///
+    /// # Panics
+    ///
+    /// Panics if the semaphore is [closed](Semaphore::close). Use
+    /// [`Semaphore::acquire_owned_checked`] to handle that case.
+    ///
     /// # Examples
     ///
     /// ```
@@ -313,18 +952,283 @@ impl Semaphore {
     /// }
     /// # }
     /// ```
-    pub async fn acquire_owned(self: Arc<Self>, permits: u32) -> OwnedSemaphorePermit {
-        self.s.acquire(permits).await;
-        OwnedSemaphorePermit { sem: self, permits }
+    pub fn acquire_owned(self: Arc<Self>, permits: u32) -> AcquireOwned {
+        AcquireOwned::new(self, permits)
     }
-}
 
-/// A permit from the semaphore.
-///
-/// This type is created by the [`acquire`] and [`try_acquire`] methods on [`Semaphore`].
-/// When the permit is dropped, the permits will be returned to the semaphore unless
-/// [`forget`] is called.
-///
+    /// Acquires `n` permits from the semaphore, or returns an error if the semaphore is closed.
+    ///
+    /// The semaphore must be wrapped in an [`Arc`] to call this method.
+    ///
+    /// This method behaves like [`Semaphore::acquire_owned`], except that it returns
+    /// `Err(Closed)` instead of panicking if the semaphore is [closed](Semaphore::close) before
+    /// the permits are acquired.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `acquire_owned_checked` makes you lose your place in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Closed;
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(1));
+    /// let permit = sem.clone().acquire_owned_checked(1).await.unwrap();
+    ///
+    /// sem.close();
+    /// let err = sem.clone().acquire_owned_checked(1).await.unwrap_err();
+    /// assert_eq!(err, Closed);
+    /// # }
+    /// ```
+    pub async fn acquire_owned_checked(
+        self: Arc<Self>,
+        permits: u32,
+    ) -> Result<OwnedSemaphorePermit, Closed> {
+        let trace = internal::AcquireTrace::start("semaphore", &self.s, permits);
+        self.reclaiming(self.s.acquire(permits))
+            .await
+            .map_err(|_| Closed)?;
+        trace.granted(&self.s, permits);
+        Ok(OwnedSemaphorePermit { sem: self, permits })
+    }
+
+    /// Acquires `n` permits from the semaphore at once, to be handed out one at a time.
+    ///
+    /// This amortizes the cost of acquiring permits in a hot loop: permits taken from the returned
+    /// [`PermitBatch`] don't touch the semaphore until they are released. Any permits that are not
+    /// taken are released when the batch is dropped.
+    ///
+    /// The `semaphore_batch` benchmark (`cargo bench -p mea --bench semaphore_batch`) runs 1 to 16
+    /// tasks taking one permit per item, on a Tokio runtime with 4 worker threads. Run on a
+    /// single-core machine, batches of 8 to 32 permits handled from a fifth to three fifths more
+    /// items per second than acquiring each permit. The gain is bounded since every taken permit
+    /// is still released to the semaphore on drop.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method uses a queue to fairly distribute permits in the order they were requested.
+    /// Cancelling a call to `reserve_batch` makes you lose your place in the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the semaphore is [closed](Semaphore::close).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(4);
+    /// let mut batch = sem.reserve_batch(3).await;
+    /// assert_eq!(sem.available_permits(), 1);
+    ///
+    /// let p1 = batch.take().unwrap();
+    /// let p2 = batch.take().unwrap();
+    /// assert_eq!(batch.remaining(), 1);
+    ///
+    /// drop(batch); // releases the untaken permit
+    /// assert_eq!(sem.available_permits(), 2);
+    /// drop((p1, p2));
+    /// assert_eq!(sem.available_permits(), 4);
+    /// # }
+    /// ```
+    pub async fn reserve_batch(&self, permits: u32) -> PermitBatch<'_> {
+        self.reclaiming(self.s.acquire(permits))
+            .await
+            .expect("Semaphore is closed");
+        PermitBatch {
+            sem: self,
+            remaining: permits,
+        }
+    }
+}
+
+/// A batch of permits reserved from the semaphore, handed out one at a time.
+///
+/// This type is created by the [`reserve_batch`] method. When the batch is dropped, the permits
+/// that have not been taken are returned to the semaphore.
+///
+/// [`reserve_batch`]: Semaphore::reserve_batch
+#[must_use = "permits are released immediately when dropped"]
+#[derive(Debug)]
+pub struct PermitBatch<'a> {
+    sem: &'a Semaphore,
+    remaining: u32,
+}
+
+impl<'a> PermitBatch<'a> {
+    /// Takes a single permit from the batch, or returns `None` if the batch is exhausted.
+    ///
+    /// The returned permit is released to the semaphore when dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(1);
+    /// let mut batch = sem.reserve_batch(1).await;
+    /// assert!(batch.take().is_some());
+    /// assert!(batch.take().is_none());
+    /// # }
+    /// ```
+    pub fn take(&mut self) -> Option<SemaphorePermit<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(SemaphorePermit {
+            sem: self.sem,
+            permits: 1,
+        })
+    }
+
+    /// Returns the number of permits that have not been taken from the batch.
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+}
+
+impl Drop for PermitBatch<'_> {
+    fn drop(&mut self) {
+        self.sem.s.release(self.remaining);
+    }
+}
+
+/// A handle to a cancellation scope, used by [`Semaphore::acquire_scoped`].
+///
+/// The scope is cancelled once the future returned by [`cancelled`] completes.
+///
+/// [`cancelled`]: Cancellation::cancelled
+pub trait Cancellation {
+    /// The future returned by [`cancelled`](Cancellation::cancelled).
+    type Cancelled<'a>: Future<Output = ()>
+    where
+        Self: 'a;
+
+    /// Returns a future that completes when the scope is cancelled.
+    fn cancelled(&self) -> Self::Cancelled<'_>;
+}
+
+/// The order in which a [`Semaphore`] hands out permits to waiting tasks.
+///
+/// The policy only orders the tasks that have to wait: permits that are immediately available are
+/// taken by whoever asks first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Policy {
+    /// The task that has waited longest gets permits first. This is fair: every waiter is
+    /// eventually served.
+    #[default]
+    Fifo,
+    /// The task that started waiting most recently gets permits first.
+    ///
+    /// This favors tasks whose state is still hot in cache, at the cost of fairness: under
+    /// sustained contention, a task that has waited for a while may be starved by newer ones.
+    Lifo,
+}
+
+/// An error returned from [`Semaphore::acquire_scoped`] when the scope is cancelled before the
+/// permits are acquired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("acquire cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// An error returned from [`Semaphore::acquire_bounded_queue`] when too many tasks are already
+/// waiting for permits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rejected;
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("too many tasks waiting for permits")
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+/// An error returned from [`Semaphore::acquire_checked`] and
+/// [`Semaphore::acquire_owned_checked`] when the semaphore is [closed](Semaphore::close).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("semaphore closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+/// An error returned from [`Semaphore::try_acquire_checked`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryAcquireError {
+    /// Not enough permits are available at the moment.
+    NoPermits,
+    /// The semaphore is [closed](Semaphore::close).
+    Closed,
+}
+
+impl fmt::Display for TryAcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryAcquireError::NoPermits => f.write_str("no permits available"),
+            TryAcquireError::Closed => f.write_str("semaphore closed"),
+        }
+    }
+}
+
+impl std::error::Error for TryAcquireError {}
+
+/// An error returned from [`Semaphore::try_release`] when the number of permits would exceed the
+/// maximum of the semaphore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReleaseOverflow {
+    max_permits: u32,
+}
+
+impl ReleaseOverflow {
+    /// Returns the maximum number of permits of the semaphore, see [`Semaphore::max_permits`].
+    pub fn max_permits(&self) -> u32 {
+        self.max_permits
+    }
+}
+
+impl fmt::Display for ReleaseOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "number of permits would exceed the maximum ({})",
+            self.max_permits
+        )
+    }
+}
+
+impl std::error::Error for ReleaseOverflow {}
+
+/// A permit from the semaphore.
+///
+/// This type is created by the [`acquire`] and [`try_acquire`] methods on [`Semaphore`].
+/// When the permit is dropped, the permits will be returned to the semaphore unless
+/// [`forget`] is called.
+///
 /// [`acquire`]: Semaphore::acquire
 /// [`try_acquire`]: Semaphore::try_acquire
 /// [`forget`]: SemaphorePermit::forget
@@ -335,7 +1239,7 @@ pub struct SemaphorePermit<'a> {
     permits: u32,
 }
 
-impl SemaphorePermit<'_> {
+impl<'a> SemaphorePermit<'a> {
     /// Forgets the permit **without** releasing it back to the semaphore.
     ///
     /// This can be used to permanently reduce the number of permits available
@@ -360,9 +1264,36 @@ impl SemaphorePermit<'_> {
     /// assert_eq!(sem.available_permits(), 5);
     /// ```
     pub fn forget(mut self) {
+        self.sem.shrink(self.permits);
         self.permits = 0;
     }
 
+    /// Forgets up to `n` of the permits **without** releasing them back to the semaphore, and
+    /// returns the number of permits actually forgotten.
+    ///
+    /// The remaining permits are still released when this permit is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(5);
+    /// let mut permit = sem.try_acquire(4).unwrap();
+    /// assert_eq!(permit.forget_partial(1), 1);
+    /// assert_eq!(permit.permits(), 3);
+    /// assert_eq!(permit.forget_partial(5), 3);
+    ///
+    /// drop(permit);
+    /// assert_eq!(sem.available_permits(), 1);
+    /// ```
+    pub fn forget_partial(&mut self, n: u32) -> u32 {
+        let forgotten = n.min(self.permits);
+        self.sem.shrink(forgotten);
+        self.permits -= forgotten;
+        forgotten
+    }
+
     /// Returns the number of permits this permit holds.
     ///
     /// # Examples
@@ -377,11 +1308,77 @@ impl SemaphorePermit<'_> {
     pub fn permits(&self) -> u32 {
         self.permits
     }
+
+    /// Splits `n` permits off this permit into a new permit of the same semaphore.
+    ///
+    /// Returns `None` if this permit holds fewer than `n` permits. Each permit releases only its
+    /// own share when dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(5);
+    /// let mut permit = sem.try_acquire(3).unwrap();
+    /// let child = permit.split(2).unwrap();
+    /// assert_eq!(permit.permits(), 1);
+    /// assert_eq!(child.permits(), 2);
+    /// assert!(permit.split(2).is_none());
+    ///
+    /// drop(child);
+    /// assert_eq!(sem.available_permits(), 4);
+    /// ```
+    pub fn split(&mut self, n: u32) -> Option<SemaphorePermit<'a>> {
+        self.permits = self.permits.checked_sub(n)?;
+        Some(SemaphorePermit {
+            sem: self.sem,
+            permits: n,
+        })
+    }
+
+    /// Merges the permits held by `other` into this permit.
+    ///
+    /// Dropping this permit afterward releases the permits of both.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two permits are not from the same semaphore.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(5);
+    /// let mut permit = sem.try_acquire(1).unwrap();
+    /// permit.merge(sem.try_acquire(2).unwrap());
+    /// assert_eq!(permit.permits(), 3);
+    ///
+    /// drop(permit);
+    /// assert_eq!(sem.available_permits(), 5);
+    /// ```
+    pub fn merge(&mut self, mut other: SemaphorePermit<'a>) {
+        assert!(
+            std::ptr::eq(self.sem, other.sem),
+            "merging permits of different semaphores"
+        );
+        self.permits += std::mem::take(&mut other.permits);
+    }
+
+    /// Returns a borrowed view of this permit, see [`OwnedSemaphorePermit::as_ref`].
+    pub fn as_ref(&self) -> SemaphorePermitRef<'_> {
+        SemaphorePermitRef {
+            sem: self.sem,
+            permits: self.permits,
+        }
+    }
 }
 
 impl Drop for SemaphorePermit<'_> {
     fn drop(&mut self) {
-        self.sem.release(self.permits);
+        self.sem.s.release(self.permits);
+        internal::trace_release("semaphore", &self.sem.s, self.permits);
     }
 }
 
@@ -422,9 +1419,38 @@ impl OwnedSemaphorePermit {
     /// assert_eq!(sem.available_permits(), 5);
     /// ```
     pub fn forget(mut self) {
+        self.sem.shrink(self.permits);
         self.permits = 0;
     }
 
+    /// Forgets up to `n` of the permits **without** releasing them back to the semaphore, and
+    /// returns the number of permits actually forgotten.
+    ///
+    /// The remaining permits are still released when this permit is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(5));
+    /// let mut permit = sem.clone().try_acquire_owned(4).unwrap();
+    /// assert_eq!(permit.forget_partial(1), 1);
+    /// assert_eq!(permit.permits(), 3);
+    /// assert_eq!(permit.forget_partial(5), 3);
+    ///
+    /// drop(permit);
+    /// assert_eq!(sem.available_permits(), 1);
+    /// ```
+    pub fn forget_partial(&mut self, n: u32) -> u32 {
+        let forgotten = n.min(self.permits);
+        self.sem.shrink(forgotten);
+        self.permits -= forgotten;
+        forgotten
+    }
+
     /// Returns the number of permits this permit holds.
     ///
     /// # Examples
@@ -439,10 +1465,193 @@ impl OwnedSemaphorePermit {
     pub fn permits(&self) -> u32 {
         self.permits
     }
+
+    /// Returns the semaphore this permit belongs to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(2));
+    /// let permit = sem.clone().try_acquire_owned(1).unwrap();
+    /// assert!(Arc::ptr_eq(permit.semaphore(), &sem));
+    /// ```
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.sem
+    }
+
+    /// Releases the permits, and returns the semaphore this permit belonged to.
+    ///
+    /// The permits are released before this method returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let permit = Arc::new(Semaphore::new(2)).try_acquire_owned(1).unwrap();
+    /// let sem = permit.into_semaphore();
+    /// assert_eq!(sem.available_permits(), 2);
+    /// ```
+    pub fn into_semaphore(self) -> Arc<Semaphore> {
+        let sem = self.sem.clone();
+        drop(self);
+        sem
+    }
+
+    /// Splits `n` permits off this permit into a new permit of the same semaphore.
+    ///
+    /// Returns `None` if this permit holds fewer than `n` permits. Each permit releases only its
+    /// own share when dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(5));
+    /// let mut permit = sem.clone().try_acquire_owned(3).unwrap();
+    /// let child = permit.split(2).unwrap();
+    /// assert_eq!(permit.permits(), 1);
+    /// assert!(permit.split(2).is_none());
+    ///
+    /// std::thread::spawn(move || drop(child)).join().unwrap();
+    /// assert_eq!(sem.available_permits(), 4);
+    /// ```
+    pub fn split(&mut self, n: u32) -> Option<OwnedSemaphorePermit> {
+        self.permits = self.permits.checked_sub(n)?;
+        Some(OwnedSemaphorePermit {
+            sem: self.sem.clone(),
+            permits: n,
+        })
+    }
+
+    /// Merges the permits held by `other` into this permit.
+    ///
+    /// Dropping this permit afterward releases the permits of both.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two permits are not from the same semaphore.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(5));
+    /// let mut permit = sem.clone().try_acquire_owned(1).unwrap();
+    /// permit.merge(sem.clone().try_acquire_owned(2).unwrap());
+    /// assert_eq!(permit.permits(), 3);
+    ///
+    /// drop(permit);
+    /// assert_eq!(sem.available_permits(), 5);
+    /// ```
+    pub fn merge(&mut self, mut other: OwnedSemaphorePermit) {
+        assert!(
+            Arc::ptr_eq(&self.sem, &other.sem),
+            "merging permits of different semaphores"
+        );
+        self.permits += std::mem::take(&mut other.permits);
+    }
+
+    /// Binds the permit to the given future, so that the permit is held for as long as the future
+    /// runs.
+    ///
+    /// The permit is released when the returned future completes, or when it is dropped before
+    /// completion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(1));
+    /// let permit = sem.clone().acquire_owned(1).await;
+    /// let handle = tokio::spawn(permit.bind(async {
+    ///     // perform task...
+    /// }));
+    ///
+    /// handle.await.unwrap();
+    /// assert_eq!(sem.available_permits(), 1);
+    /// # }
+    /// ```
+    pub async fn bind<F: Future>(self, fut: F) -> F::Output {
+        let _permit = self;
+        fut.await
+    }
+
+    /// Returns a borrowed view of this permit.
+    ///
+    /// The view does not release anything when dropped: this permit still releases the permits
+    /// when it is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    /// use mea::semaphore::SemaphorePermitRef;
+    ///
+    /// fn describe(permit: SemaphorePermitRef<'_>) -> String {
+    ///     format!(
+    ///         "{} of {}",
+    ///         permit.permits(),
+    ///         permit.semaphore().available_permits()
+    ///     )
+    /// }
+    ///
+    /// let sem = Arc::new(Semaphore::new(3));
+    /// let permit = sem.clone().try_acquire_owned(2).unwrap();
+    /// assert_eq!(describe(permit.as_ref()), "2 of 1");
+    /// ```
+    pub fn as_ref(&self) -> SemaphorePermitRef<'_> {
+        SemaphorePermitRef {
+            sem: &self.sem,
+            permits: self.permits,
+        }
+    }
 }
 
 impl Drop for OwnedSemaphorePermit {
     fn drop(&mut self) {
-        self.sem.release(self.permits);
+        self.sem.s.release(self.permits);
+        internal::trace_release("semaphore", &self.sem.s, self.permits);
+    }
+}
+
+/// A borrowed view of a [`SemaphorePermit`] or an [`OwnedSemaphorePermit`].
+///
+/// This type is created by the `as_ref` methods of the permits. It lets an API accept either
+/// kind of permit. Dropping the view does not release any permits.
+#[derive(Debug, Clone, Copy)]
+pub struct SemaphorePermitRef<'a> {
+    sem: &'a Semaphore,
+    permits: u32,
+}
+
+impl<'a> SemaphorePermitRef<'a> {
+    /// Returns the number of permits the viewed permit holds.
+    pub fn permits(&self) -> u32 {
+        self.permits
+    }
+
+    /// Returns the semaphore the viewed permit was acquired from.
+    pub fn semaphore(&self) -> &'a Semaphore {
+        self.sem
     }
 }
@@ -0,0 +1,154 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Property tests of permit conservation.
+//!
+//! Each case runs a random sequence of operations against a single semaphore. Pending acquires
+//! are driven by polling them by hand on the test thread, so every run is deterministic and
+//! shrinks well. A model tracks the permits the semaphore owns, so that at every step:
+//!
+//! * `available + held <= total`, where the difference is the permits assigned to waiters that have
+//!   not completed yet;
+//! * `available + held == total` whenever no acquire is pending.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use tokio_test::task::spawn;
+
+use crate::semaphore::Semaphore;
+
+/// An upper bound of the permits the semaphore owns, to stay clear of the overflow panic.
+const MAX_TOTAL: u32 = 64;
+
+#[derive(Debug, Clone)]
+enum Op {
+    TryAcquire(u32),
+    Acquire(u32),
+    /// Polls the pending acquire at the given index, modulo the number of pending acquires.
+    Poll(usize),
+    /// Cancels the pending acquire at the given index.
+    Cancel(usize),
+    /// Drops the held permit at the given index.
+    Drop(usize),
+    /// Forgets the held permit at the given index.
+    ForgetPermit(usize),
+    /// Splits permits off the held permit at the given index.
+    Split(usize, u32),
+    /// Merges the held permit at the second index into the one at the first index.
+    Merge(usize, usize),
+    Release(u32),
+    Forget(u32),
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (0..4u32).prop_map(Op::TryAcquire),
+        (0..4u32).prop_map(Op::Acquire),
+        any::<usize>().prop_map(Op::Poll),
+        any::<usize>().prop_map(Op::Cancel),
+        any::<usize>().prop_map(Op::Drop),
+        any::<usize>().prop_map(Op::ForgetPermit),
+        (any::<usize>(), 0..4u32).prop_map(|(i, n)| Op::Split(i, n)),
+        (any::<usize>(), any::<usize>()).prop_map(|(i, j)| Op::Merge(i, j)),
+        (0..4u32).prop_map(Op::Release),
+        (0..4u32).prop_map(Op::Forget),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn permits_are_conserved(initial in 0..8u32, ops in vec(op(), 0..64)) {
+        let sem = Semaphore::new(initial);
+        let mut total = initial;
+        let mut held = Vec::new();
+        let mut pending = Vec::new();
+
+        for op in ops {
+            match op {
+                Op::TryAcquire(n) => {
+                    if let Some(permit) = sem.try_acquire(n) {
+                        held.push(permit);
+                    }
+                }
+                Op::Acquire(n) => {
+                    let mut acquire = spawn(sem.acquire(n));
+                    match acquire.poll() {
+                        std::task::Poll::Ready(permit) => held.push(permit),
+                        std::task::Poll::Pending => pending.push(acquire),
+                    }
+                }
+                Op::Poll(i) if !pending.is_empty() => {
+                    let i = i % pending.len();
+                    if let std::task::Poll::Ready(permit) = pending[i].poll() {
+                        held.push(permit);
+                        drop(pending.remove(i));
+                    }
+                }
+                Op::Cancel(i) if !pending.is_empty() => {
+                    drop(pending.remove(i % pending.len()));
+                }
+                Op::Drop(i) if !held.is_empty() => {
+                    drop(held.remove(i % held.len()));
+                }
+                Op::ForgetPermit(i) if !held.is_empty() => {
+                    let permit = held.remove(i % held.len());
+                    total -= permit.permits();
+                    permit.forget();
+                }
+                Op::Split(i, n) if !held.is_empty() => {
+                    let i = i % held.len();
+                    let before = held[i].permits();
+                    match held[i].split(n) {
+                        Some(permit) => {
+                            prop_assert_eq!(held[i].permits() + permit.permits(), before);
+                            held.push(permit);
+                        }
+                        None => prop_assert!(n > before),
+                    }
+                }
+                Op::Merge(i, j) if held.len() >= 2 => {
+                    let other = held.remove(j % held.len());
+                    let i = i % held.len();
+                    let expected = held[i].permits() + other.permits();
+                    held[i].merge(other);
+                    prop_assert_eq!(held[i].permits(), expected);
+                }
+                Op::Release(n) if total + n <= MAX_TOTAL => {
+                    sem.release(n);
+                    total += n;
+                }
+                Op::Forget(n) => {
+                    let forgotten = sem.forget(n);
+                    prop_assert!(forgotten <= n);
+                    total -= forgotten;
+                }
+                _ => {}
+            }
+
+            let held_permits: u32 = held.iter().map(|p| p.permits()).sum();
+            let available = sem.available_permits();
+            prop_assert!(available + held_permits <= total);
+            if pending.is_empty() {
+                prop_assert_eq!(available + held_permits, total);
+            }
+        }
+
+        // cancelled acquires give back the permits assigned to them
+        drop(pending);
+        let held_permits: u32 = held.iter().map(|p| p.permits()).sum();
+        prop_assert_eq!(sem.available_permits() + held_permits, total);
+        drop(held);
+        prop_assert_eq!(sem.available_permits(), total);
+    }
+}
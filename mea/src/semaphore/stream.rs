@@ -0,0 +1,131 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::future::poll_fn;
+#[cfg(feature = "stream")]
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::ready;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::semaphore::AcquireOwned;
+use crate::semaphore::OwnedSemaphorePermit;
+use crate::semaphore::Semaphore;
+
+impl Semaphore {
+    /// Returns a stream of owned permits, each holding `permits` permits.
+    ///
+    /// The semaphore must be wrapped in an [`Arc`] to call this method.
+    ///
+    /// The stream yields a permit whenever `permits` permits can be acquired, so a consumer that
+    /// holds on to the permits is backpressured by the semaphore. The stream ends when the
+    /// semaphore is [closed](Semaphore::close).
+    ///
+    /// With the `stream` feature, [`PermitStream`] implements [`futures_core::Stream`], so it
+    /// composes with stream combinators such as `buffer_unordered`.
+    ///
+    /// [`futures_core::Stream`]: https://docs.rs/futures-core/latest/futures_core/stream/trait.Stream.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(2));
+    /// let mut permits = sem.clone().permit_stream(1);
+    ///
+    /// let mut handles = Vec::new();
+    /// for i in 0..5 {
+    ///     let permit = permits.next().await.unwrap();
+    ///     handles.push(tokio::spawn(permit.bind(async move { i * 2 })));
+    /// }
+    /// for handle in handles {
+    ///     handle.await.unwrap();
+    /// }
+    ///
+    /// sem.close();
+    /// assert!(permits.next().await.is_none());
+    /// # }
+    /// ```
+    pub fn permit_stream(self: Arc<Self>, permits: u32) -> PermitStream {
+        PermitStream {
+            sem: self,
+            permits,
+            acquire: None,
+        }
+    }
+}
+
+/// A stream of owned permits from a semaphore.
+///
+/// This type is created by the [`permit_stream`] method. Dropping the stream while it waits for
+/// permits gives up its place in the queue, and gives back any permits already assigned to it.
+///
+/// [`permit_stream`]: Semaphore::permit_stream
+pub struct PermitStream {
+    sem: Arc<Semaphore>,
+    permits: u32,
+    /// The pending acquisition, kept across polls so that the stream keeps its place in the queue.
+    acquire: Option<AcquireOwned>,
+}
+
+impl fmt::Debug for PermitStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PermitStream")
+            .field("sem", &self.sem)
+            .field("permits", &self.permits)
+            .field("pending", &self.acquire.is_some())
+            .finish()
+    }
+}
+
+impl PermitStream {
+    /// Waits for the next permit, or returns `None` if the semaphore is closed.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe: the stream keeps its place in the queue, and the permit is
+    /// returned by the next call.
+    pub async fn next(&mut self) -> Option<OwnedSemaphorePermit> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Polls for the next permit.
+    ///
+    /// Returns `Ready(None)` if the semaphore is closed.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<OwnedSemaphorePermit>> {
+        let acquire = self.acquire.get_or_insert_with(|| {
+            self.sem.reclaim_expired_leases();
+            AcquireOwned::new(self.sem.clone(), self.permits)
+        });
+        let acquired = ready!(acquire.poll_checked(cx));
+        self.acquire = None;
+        Poll::Ready(acquired.ok())
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for PermitStream {
+    type Item = OwnedSemaphorePermit;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next(cx)
+    }
+}
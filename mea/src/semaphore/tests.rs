@@ -12,10 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::task::Context;
+use std::task::Wake;
+use std::task::Waker;
+use std::time::Duration;
 use std::vec::Vec;
 
+use tokio_test::assert_pending;
+use tokio_test::assert_ready;
+use tokio_test::task::spawn;
+
 use super::*;
+use crate::signal::Signal;
 
 #[test]
 fn no_permits() {
@@ -72,6 +85,85 @@ fn forget() {
     assert!(sem.try_acquire(1).is_none());
 }
 
+#[tokio::test]
+async fn bind_releases_on_completion() {
+    let sem = Arc::new(Semaphore::new(2));
+    let permit = sem.clone().acquire_owned(2).await;
+    let fut = permit.bind(async { 42 });
+    assert_eq!(sem.available_permits(), 0);
+    assert_eq!(tokio::spawn(fut).await.unwrap(), 42);
+    assert_eq!(sem.available_permits(), 2);
+}
+
+#[test]
+fn bind_releases_on_cancel() {
+    let sem = Arc::new(Semaphore::new(1));
+    let permit = sem.clone().try_acquire_owned(1).unwrap();
+    let fut = permit.bind(std::future::pending::<()>());
+    assert_eq!(sem.available_permits(), 0);
+    drop(fut);
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[test]
+fn acquire_scoped_cancelled_while_parked() {
+    let sem = Semaphore::new(2);
+    let scope = Signal::new();
+    let held = sem.try_acquire(1).unwrap();
+
+    let mut scoped = spawn(sem.acquire_scoped(2, &scope));
+    assert_pending!(scoped.poll());
+    let mut next = spawn(sem.acquire(1));
+    assert_pending!(next.poll());
+
+    scope.notify();
+    assert!(scoped.is_woken());
+    assert_eq!(assert_ready!(scoped.poll()).unwrap_err(), Cancelled);
+
+    // the permit tentatively assigned to the cancelled request goes to the next waiter
+    assert!(next.is_woken());
+    let p = assert_ready!(next.poll());
+    drop(p);
+    drop(held);
+    assert_eq!(sem.available_permits(), 2);
+}
+
+#[test]
+fn cancel_acquire_returns_only_assigned_permits() {
+    let sem = Semaphore::new(1);
+    let held = sem.try_acquire(1).unwrap();
+
+    // nothing has been assigned yet
+    let mut f = spawn(sem.acquire(1));
+    assert_pending!(f.poll());
+    drop(f);
+    assert_eq!(sem.available_permits(), 0);
+
+    // all permits have been assigned, but the future is dropped before being polled again
+    let mut f = spawn(sem.acquire(1));
+    assert_pending!(f.poll());
+    drop(held);
+    assert!(f.is_woken());
+    drop(f);
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[tokio::test]
+async fn reserve_batch_partial_consumption() {
+    let sem = Semaphore::new(10);
+    let mut batch = sem.reserve_batch(5).await;
+    assert_eq!(sem.available_permits(), 5);
+
+    let taken = (0..3).map(|_| batch.take().unwrap()).collect::<Vec<_>>();
+    assert_eq!(batch.remaining(), 2);
+    assert_eq!(sem.available_permits(), 5);
+
+    drop(batch);
+    assert_eq!(sem.available_permits(), 7);
+    drop(taken);
+    assert_eq!(sem.available_permits(), 10);
+}
+
 #[tokio::test]
 async fn stress_test() {
     let sem = Arc::new(Semaphore::new(5));
@@ -123,3 +215,790 @@ fn no_panic_at_max_permits() {
     let s = Semaphore::new(u32::MAX - 1);
     s.release(1);
 }
+
+#[test]
+fn release_up_to_max_permits() {
+    let s = Semaphore::new(Semaphore::MAX_PERMITS - 2);
+    s.release(2);
+    assert_eq!(s.available_permits(), Semaphore::MAX_PERMITS);
+    assert_eq!(
+        s.try_release(1).unwrap_err().max_permits(),
+        Semaphore::MAX_PERMITS
+    );
+}
+
+#[test]
+#[should_panic(expected = "failed to release permits")]
+fn release_beyond_max_permits() {
+    let s = Semaphore::new(Semaphore::MAX_PERMITS);
+    s.release(1);
+}
+
+#[test]
+fn try_release_overflow_adds_nothing() {
+    let s = Semaphore::new(1);
+    let err = s.try_release(u32::MAX).unwrap_err();
+    assert_eq!(err.max_permits(), u32::MAX);
+    assert_eq!(s.available_permits(), 1);
+    assert!(s.try_release(u32::MAX - 1).is_ok());
+    assert_eq!(s.available_permits(), u32::MAX);
+    assert!(s.try_release(0).is_ok());
+}
+
+#[tokio::test]
+async fn expired_lease_is_reclaimed_once() {
+    let sem = Semaphore::new(2);
+    let stalled = sem.acquire_lease(1, Duration::from_millis(10)).await;
+    let renewed = sem.acquire_lease(1, Duration::from_millis(50)).await;
+    assert_eq!(sem.available_permits(), 0);
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert!(renewed.renew());
+
+    // the un-renewed lease is reclaimed by the next acquirer
+    let permit = sem.acquire(1).await;
+    assert!(stalled.is_reclaimed());
+    assert!(!stalled.renew());
+    assert!(!renewed.is_reclaimed());
+
+    // dropping a reclaimed lease must not release its permits again
+    drop(stalled);
+    assert_eq!(sem.available_permits(), 0);
+    drop(permit);
+    drop(renewed);
+    assert_eq!(sem.available_permits(), 2);
+}
+
+#[test]
+fn expired_lease_is_reclaimed_by_parked_waiter() {
+    let sem = Semaphore::new(1);
+    let stalled = pollster::block_on(sem.acquire_lease(1, Duration::from_millis(10)));
+    let mut waiter = spawn(sem.acquire(1));
+    assert_pending!(waiter.poll());
+    assert!(!waiter.is_woken());
+
+    // the only acquirer is woken at the deadline, and reclaims the lease on its next poll
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(waiter.is_woken());
+    drop(assert_ready!(waiter.poll()));
+    assert!(stalled.is_reclaimed());
+}
+
+#[test]
+fn lease_granted_after_waiter_parks_is_reclaimed() {
+    let sem = Semaphore::new(1);
+    let permit = sem.try_acquire(1).unwrap();
+    let mut lease = spawn(sem.acquire_lease(1, Duration::from_millis(10)));
+    let mut waiter = spawn(sem.acquire_checked(1));
+    assert_pending!(lease.poll());
+    assert_pending!(waiter.poll());
+
+    // the waiter parked while there was no lease, so it must be woken to learn the deadline
+    drop(permit);
+    let stalled = assert_ready!(lease.poll());
+    assert!(waiter.is_woken());
+    assert_pending!(waiter.poll());
+
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(waiter.is_woken());
+    drop(assert_ready!(waiter.poll()).unwrap());
+    assert!(stalled.is_reclaimed());
+}
+
+#[test]
+fn capped_semaphore_tracks_forget() {
+    let sem = Semaphore::new_capped(3);
+    let p = sem.try_acquire(2).unwrap();
+    p.forget();
+    assert_eq!(sem.forget(1), 1);
+    assert_eq!(sem.available_permits(), 0);
+
+    // forgotten permits can be added back, up to the cap
+    sem.release(3);
+    assert_eq!(sem.available_permits(), 3);
+}
+
+#[test]
+fn capped_semaphore_try_release_beyond_cap_adds_nothing() {
+    let sem = Semaphore::new_capped(3);
+    let p = sem.try_acquire(2).unwrap();
+    p.forget();
+    let err = sem.try_release(3).unwrap_err();
+    assert_eq!(err.max_permits(), 3);
+    assert_eq!(sem.available_permits(), 1);
+
+    // what fits under the cap can still be added
+    assert!(sem.try_release(2).is_ok());
+    assert_eq!(sem.available_permits(), 3);
+    assert!(sem.try_release(1).is_err());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "would exceed the cap")]
+fn capped_semaphore_over_release() {
+    let sem = Semaphore::new_capped(2);
+    let _p = sem.try_acquire(1).unwrap();
+    sem.release(1);
+}
+
+#[test]
+fn acquire_bounded_queue_rejects_excess() {
+    let sem = Semaphore::new(2);
+    let permit = sem.try_acquire(2).unwrap();
+
+    let mut queued: Vec<_> = (0..2)
+        .map(|_| spawn(sem.acquire_bounded_queue(1, 2)))
+        .collect();
+    for q in queued.iter_mut() {
+        assert_pending!(q.poll());
+    }
+
+    // the queue is full, so further acquirers are rejected rather than queued
+    let mut rejected = spawn(sem.acquire_bounded_queue(1, 2));
+    assert_eq!(assert_ready!(rejected.poll()).unwrap_err(), Rejected);
+
+    drop(permit);
+    for q in queued.iter_mut() {
+        assert!(assert_ready!(q.poll()).is_ok());
+    }
+}
+
+#[test]
+fn watch_available_observes_changes() {
+    let sem = Semaphore::new(3);
+    let mut watch = sem.watch_available();
+    let mut next = spawn(async { watch.next().await });
+    assert_eq!(assert_ready!(next.poll()), 3);
+    drop(next);
+
+    let mut next = spawn(async { watch.next().await });
+    assert_pending!(next.poll());
+    let p1 = sem.try_acquire(1).unwrap();
+    assert!(next.is_woken());
+    assert_eq!(assert_ready!(next.poll()), 2);
+    drop(next);
+
+    // changes are coalesced into the latest number
+    let p2 = sem.try_acquire(2).unwrap();
+    drop(p1);
+    let mut next = spawn(async { watch.next().await });
+    assert_eq!(assert_ready!(next.poll()), 1);
+    drop(next);
+
+    // a change back to the last yielded number is not observed
+    let mut next = spawn(async { watch.next().await });
+    assert_pending!(next.poll());
+    let p3 = sem.try_acquire(1).unwrap();
+    drop(p3);
+    assert_pending!(next.poll());
+    drop(p2);
+    assert_eq!(assert_ready!(next.poll()), 3);
+    drop(next);
+
+    // a dropped stream is deregistered, so its waker never fires again
+    struct CountWakes(AtomicUsize);
+    impl Wake for CountWakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+    let waker = Waker::from(wakes.clone());
+    {
+        let mut next = pin!(watch.next());
+        assert!(next
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+    }
+    drop(watch);
+    sem.release(1);
+    assert_eq!(sem.available_permits(), 4);
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn failed_try_acquire_releases_nothing() {
+    let sem = Arc::new(Semaphore::new(1));
+    let _p = sem.try_acquire(1).unwrap();
+    assert!(sem.try_acquire(1).is_none());
+    assert!(sem.clone().try_acquire_owned(1).is_none());
+    assert_eq!(sem.available_permits(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn semaphore_group_bounds_global_sum() {
+    const GLOBAL: u32 = 5;
+    let group = SemaphoreGroup::new(GLOBAL);
+    let members: Vec<_> = (0..4).map(|_| Arc::new(group.semaphore(3))).collect();
+    let held = Arc::new(AtomicU32::new(0));
+
+    let mut handles = Vec::new();
+    for member in &members {
+        for _ in 0..8 {
+            let member = member.clone();
+            let held = held.clone();
+            handles.push(tokio::spawn(async move {
+                for _ in 0..16 {
+                    let permit = member.acquire(1).await;
+                    let now = held.fetch_add(1, Ordering::SeqCst) + 1;
+                    assert!(now <= GLOBAL, "{now} permits held across the group");
+                    tokio::task::yield_now().await;
+                    held.fetch_sub(1, Ordering::SeqCst);
+                    drop(permit);
+                }
+            }));
+        }
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    assert_eq!(group.available_permits(), GLOBAL);
+    for member in &members {
+        assert_eq!(member.available_permits(), 3);
+    }
+}
+
+#[test]
+fn semaphore_group_release_returns_global_capacity() {
+    let group = SemaphoreGroup::new(2);
+    let a = group.semaphore(2);
+    let b = group.semaphore(2);
+
+    let pa = a.try_acquire(2).unwrap();
+    let mut fb = spawn(b.acquire(1));
+    assert_pending!(fb.poll());
+    // the waiting member holds its local permit while queued for the global one
+    assert_eq!(b.available_permits(), 1);
+
+    drop(pa);
+    assert!(fb.is_woken());
+    let pb = assert_ready!(fb.poll());
+    assert_eq!(pb.permits(), 1);
+    assert_eq!(group.available_permits(), 1);
+    assert_eq!(a.available_permits(), 2);
+
+    drop(pb);
+    drop(fb);
+    assert_eq!(group.available_permits(), 2);
+    assert_eq!(b.available_permits(), 2);
+}
+
+#[test]
+fn try_acquire_spin_succeeds_when_released_within_budget() {
+    let sem = Arc::new(Semaphore::new(1));
+    let permit = sem.clone().try_acquire_owned(1).unwrap();
+    assert!(sem.try_acquire_spin(1, 0).is_none());
+
+    let holder = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        drop(permit);
+    });
+    let permit = sem.try_acquire_spin(1, u32::MAX).unwrap();
+    holder.join().unwrap();
+    assert_eq!(permit.permits(), 1);
+    assert_eq!(sem.available_permits(), 0);
+}
+
+#[test]
+#[cfg(feature = "tracing")]
+fn tracing_events_for_contended_acquire() {
+    use std::sync::Mutex;
+
+    use tracing::field::Field;
+    use tracing::field::Visit;
+    use tracing::span;
+    use tracing::Event;
+    use tracing::Metadata;
+
+    #[derive(Clone, Default)]
+    struct Collector(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for &Collector {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0.lock().unwrap().push(format!("{value:?}"));
+            }
+        }
+    }
+
+    impl tracing::Subscriber for Collector {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut &*self);
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    let collector = Collector::default();
+    tracing::subscriber::with_default(collector.clone(), || {
+        let sem = Semaphore::new(1);
+        let held = sem.try_acquire(1).unwrap();
+        let mut waiter = spawn(sem.acquire(1));
+        assert_pending!(waiter.poll());
+        drop(held);
+        drop(assert_ready!(waiter.poll()));
+    });
+
+    let events = collector.0.lock().unwrap().clone();
+    assert_eq!(
+        events,
+        [
+            "acquire wait start",
+            "release",
+            "acquire granted",
+            "release"
+        ]
+    );
+}
+
+#[test]
+fn wfq_grants_follow_weights() {
+    let sem = WfqSemaphore::new(1, &[3, 1]);
+    let mut held = Some(assert_ready!(spawn(sem.acquire_class(1, 0)).poll()));
+
+    // saturate both classes
+    let mut waiters: Vec<_> = (0..400)
+        .flat_map(|_| [0, 1])
+        .map(|class| (class, spawn(sem.acquire_class(1, class))))
+        .collect();
+    for (_, w) in waiters.iter_mut() {
+        assert_pending!(w.poll());
+    }
+
+    let mut grants = [0u32; 2];
+    for _ in 0..400 {
+        drop(held.take());
+        let i = waiters.iter().position(|(_, w)| w.is_woken()).unwrap();
+        let (class, mut w) = waiters.swap_remove(i);
+        held = Some(assert_ready!(w.poll()));
+        grants[class] += 1;
+    }
+
+    // 3:1 shares of 400 grants, with no class starved
+    assert!((290..=310).contains(&grants[0]), "{grants:?}");
+    assert!((90..=110).contains(&grants[1]), "{grants:?}");
+}
+
+#[test]
+fn wfq_large_request_is_dispatched_promptly() {
+    const PERMITS: u32 = 1_000_000_000;
+    let sem = WfqSemaphore::new(PERMITS, &[1, 1]);
+    let held = assert_ready!(spawn(sem.acquire_class(PERMITS, 0)).poll());
+
+    // the request needs a billion rounds of quanta, which must not be played out one by one
+    let start = std::time::Instant::now();
+    let mut large = spawn(sem.acquire_class(PERMITS, 1));
+    let mut small = spawn(sem.acquire_class(1, 0));
+    assert_pending!(large.poll());
+    assert_pending!(small.poll());
+    drop(held);
+
+    // the large request has built up its deficit while waiting, so it goes first
+    assert!(large.is_woken());
+    assert!(!small.is_woken());
+    drop(assert_ready!(large.poll()));
+    assert!(small.is_woken());
+    drop(assert_ready!(small.poll()));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn wfq_cancelled_waiter_does_not_leak() {
+    let sem = WfqSemaphore::new(2, &[1, 1]);
+    let h1 = assert_ready!(spawn(sem.acquire_class(1, 0)).poll());
+    let h2 = assert_ready!(spawn(sem.acquire_class(1, 0)).poll());
+
+    let mut big = spawn(sem.acquire_class(2, 1));
+    let mut small = spawn(sem.acquire_class(1, 0));
+    assert_pending!(big.poll());
+    assert_pending!(small.poll());
+
+    // the big waiter blocks the round robin until it is cancelled
+    drop(h1);
+    assert!(!small.is_woken());
+    drop(big);
+    assert!(small.is_woken());
+    let p = assert_ready!(small.poll());
+    assert_eq!(sem.available_permits(), 0);
+
+    // permits granted to a cancelled waiter are given back
+    let mut big = spawn(sem.acquire_class(2, 1));
+    assert_pending!(big.poll());
+    drop((h2, p));
+    assert!(big.is_woken());
+    drop(big);
+    assert_eq!(sem.available_permits(), 2);
+}
+
+#[test]
+fn permit_ref_does_not_release() {
+    let sem = Arc::new(Semaphore::new(3));
+    let owned = sem.clone().try_acquire_owned(2).unwrap();
+    let borrowed = sem.try_acquire(1).unwrap();
+
+    let views = [owned.as_ref(), borrowed.as_ref()];
+    assert_eq!(views.map(|v| v.permits()), [2, 1]);
+    assert!(std::ptr::eq(views[0].semaphore(), &*sem));
+    assert_eq!(sem.available_permits(), 0);
+
+    drop(owned);
+    assert_eq!(sem.available_permits(), 2);
+    drop(borrowed);
+    assert_eq!(sem.available_permits(), 3);
+}
+
+#[tokio::test]
+async fn acquire_cooperative_lets_others_run() {
+    let progress = Arc::new(AtomicU32::new(0));
+    let competitor = {
+        let progress = progress.clone();
+        tokio::spawn(async move {
+            loop {
+                progress.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+
+    // on this single-threaded runtime, the plain fast path never yields
+    let sem = Semaphore::new(1);
+    for _ in 0..100 {
+        drop(sem.acquire(1).await);
+    }
+    assert_eq!(progress.load(Ordering::SeqCst), 0);
+
+    for _ in 0..100 {
+        drop(sem.acquire_cooperative(1, tokio::task::yield_now()).await);
+    }
+    assert!(progress.load(Ordering::SeqCst) >= 50);
+    competitor.abort();
+}
+
+#[tokio::test]
+async fn acquire_timed_out_by_runtime_timer_leaks_nothing() {
+    let sem = Arc::new(Semaphore::new(3));
+    let held = sem.acquire(2).await;
+
+    // partially assigned when the timer fires
+    let timed_out = tokio::time::timeout(Duration::from_millis(20), sem.acquire(3)).await;
+    assert!(timed_out.is_err());
+    assert_eq!(sem.available_permits(), 1);
+
+    // the abandoned request does not block later acquirers
+    let p = sem.acquire(1).await;
+    drop((held, p));
+    assert_eq!(sem.available_permits(), 3);
+    let owned = tokio::time::timeout(Duration::from_millis(20), sem.clone().acquire_owned(3)).await;
+    assert_eq!(owned.unwrap().permits(), 3);
+}
+
+#[tokio::test]
+async fn close_fails_waiters_but_keeps_acquired_permits() {
+    let sem = Arc::new(Semaphore::new(2));
+    let held = sem.acquire(2).await;
+
+    let waiter = {
+        let sem = sem.clone();
+        tokio::spawn(async move { sem.acquire_checked(1).await.map(|p| p.permits()) })
+    };
+    let owned_waiter = tokio::spawn(sem.clone().acquire_owned_checked(1));
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    sem.close();
+    assert_eq!(waiter.await.unwrap(), Err(Closed));
+    assert_eq!(owned_waiter.await.unwrap().unwrap_err(), Closed);
+
+    // the acquired permits are still released as usual
+    drop(held);
+    assert_eq!(sem.available_permits(), 2);
+    assert!(sem.try_acquire(1).is_none());
+    let err = sem.try_acquire_checked(1).unwrap_err();
+    assert_eq!(err, TryAcquireError::Closed);
+    assert_eq!(sem.acquire_checked(1).await.unwrap_err(), Closed);
+}
+
+#[test]
+fn dropped_waiters_are_unlinked_and_keep_fifo() {
+    let sem = Semaphore::new(1);
+    let holder = sem.try_acquire(1).unwrap();
+
+    let mut waiters: Vec<_> = (0..10).map(|_| Some(spawn(sem.acquire(1)))).collect();
+    for waiter in waiters.iter_mut().flatten() {
+        assert_pending!(waiter.poll());
+    }
+    for waiter in &mut waiters[3..8] {
+        *waiter = None;
+    }
+
+    drop(holder);
+    for i in [0, 1, 2, 8, 9] {
+        for (j, waiter) in waiters.iter_mut().enumerate() {
+            if let Some(waiter) = waiter {
+                if j != i {
+                    assert_pending!(waiter.poll());
+                }
+            }
+        }
+        let mut waiter = waiters[i].take().unwrap();
+        let permit = assert_ready!(waiter.poll());
+        assert_eq!(sem.available_permits(), 0);
+        drop(permit);
+    }
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[tokio::test]
+async fn acquire_with_passes_granted_permits() {
+    let sem = Semaphore::new(5);
+    let permit = sem.acquire_with(3, |granted, permit| {
+        assert_eq!(granted, 3);
+        permit
+    });
+    let permit = permit.await;
+    assert_eq!(sem.available_permits(), 2);
+
+    // dropping the permit in the closure releases it
+    let granted = sem.acquire_with(2, |granted, _| granted).await;
+    assert_eq!(granted, 2);
+    assert_eq!(sem.available_permits(), 2);
+    drop(permit);
+    assert_eq!(sem.available_permits(), 5);
+}
+
+#[test]
+#[should_panic(expected = "merging permits of different semaphores")]
+fn merge_permits_of_different_semaphores() {
+    let a = Semaphore::new(1);
+    let b = Semaphore::new(1);
+    let mut permit = a.try_acquire(1).unwrap();
+    permit.merge(b.try_acquire(1).unwrap());
+}
+
+#[test]
+fn waiters_counts_queued_acquirers() {
+    let sem = Semaphore::new(1);
+    let holder = sem.try_acquire(1).unwrap();
+
+    let mut first = spawn(sem.acquire(1));
+    let mut second = spawn(sem.acquire(1));
+    assert_pending!(first.poll());
+    assert_pending!(second.poll());
+    assert_eq!(sem.waiters(), 2);
+
+    drop(second);
+    assert_eq!(sem.waiters(), 1);
+
+    // a waiter that got all its permits no longer waits
+    drop(holder);
+    assert_eq!(sem.waiters(), 0);
+    drop(assert_ready!(first.poll()));
+    assert_eq!(sem.waiters(), 0);
+}
+
+#[test]
+fn zero_permits_never_wait() {
+    let sem = Semaphore::new(1);
+    let holder = sem.try_acquire(1).unwrap();
+    let mut waiter = spawn(sem.acquire(1));
+    assert_pending!(waiter.poll());
+
+    // zero permits neither wait behind nor wake the queued acquirer
+    let mut zero = spawn(sem.acquire(0));
+    let permit = assert_ready!(zero.poll());
+    assert_eq!(permit.permits(), 0);
+    drop(permit);
+    assert_eq!(sem.try_acquire(0).unwrap().permits(), 0);
+    assert!(!waiter.is_woken());
+    assert_eq!(sem.waiters(), 1);
+    assert_eq!(sem.available_permits(), 0);
+
+    drop(holder);
+    assert!(waiter.is_woken());
+    drop(assert_ready!(waiter.poll()));
+
+    sem.close();
+    assert!(sem.try_acquire(0).is_some());
+    assert!(sem.try_acquire(1).is_none());
+    assert!(assert_ready!(spawn(sem.acquire_checked(0)).poll()).is_ok());
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[test]
+fn acquire_all_drains_available_permits() {
+    let sem = Semaphore::new(4);
+    let held = sem.try_acquire(1).unwrap();
+
+    let all = sem.acquire_all();
+    assert_eq!(all.permits(), 3);
+    assert_eq!(sem.available_permits(), 0);
+    assert_eq!(sem.acquire_all().permits(), 0);
+
+    drop(held);
+    let rest = sem.acquire_all();
+    assert_eq!(rest.permits(), 1);
+    drop((all, rest));
+    assert_eq!(sem.available_permits(), 4);
+
+    sem.close();
+    assert_eq!(sem.acquire_all().permits(), 0);
+    assert_eq!(sem.available_permits(), 4);
+}
+
+#[test]
+fn permit_stream_keeps_its_place() {
+    let sem = Arc::new(Semaphore::new(1));
+    let mut stream = sem.clone().permit_stream(1);
+
+    let first = {
+        let mut next = spawn(stream.next());
+        assert_ready!(next.poll()).unwrap()
+    };
+    {
+        let mut next = spawn(stream.next());
+        assert_pending!(next.poll());
+    }
+
+    // the stream is queued ahead of the later acquirer
+    let mut later = spawn(sem.acquire(1));
+    assert_pending!(later.poll());
+    drop(first);
+    assert_pending!(later.poll());
+    let second = assert_ready!(spawn(stream.next()).poll()).unwrap();
+
+    // dropping a pending stream gives up its place
+    drop(second);
+    drop(assert_ready!(later.poll()));
+    let held = sem.try_acquire(1).unwrap();
+    assert_pending!(spawn(stream.next()).poll());
+    drop(stream);
+    drop(held);
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[test]
+#[cfg(feature = "stream")]
+fn permit_stream_is_a_stream() {
+    use std::future::poll_fn;
+    use std::pin::Pin;
+    use std::task::Poll;
+
+    use futures_core::Stream;
+
+    fn poll_next(stream: &mut PermitStream) -> Poll<Option<OwnedSemaphorePermit>> {
+        spawn(poll_fn(|cx| Stream::poll_next(Pin::new(&mut *stream), cx))).poll()
+    }
+
+    let sem = Arc::new(Semaphore::new(1));
+    let mut stream = sem.clone().permit_stream(1);
+    let permit = assert_ready!(poll_next(&mut stream)).unwrap();
+    assert_pending!(poll_next(&mut stream));
+    drop(permit);
+    let permit = assert_ready!(poll_next(&mut stream)).unwrap();
+    sem.close();
+    assert!(assert_ready!(poll_next(&mut stream)).is_none());
+    drop(permit);
+}
+
+#[test]
+fn acquire_futures_can_be_stored() {
+    struct Pending<'a> {
+        borrowed: Acquire<'a>,
+        owned: AcquireOwned,
+    }
+
+    let sem = Arc::new(Semaphore::new(1));
+    let holder = sem.try_acquire(1).unwrap();
+    let pending = Pending {
+        borrowed: sem.acquire(1),
+        owned: sem.clone().acquire_owned(1),
+    };
+    let mut borrowed = spawn(pending.borrowed);
+    let mut owned = spawn(pending.owned);
+    assert_pending!(borrowed.poll());
+    assert_pending!(owned.poll());
+
+    drop(holder);
+    drop(assert_ready!(borrowed.poll()));
+    assert_eq!(assert_ready!(owned.poll()).permits(), 1);
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[test]
+#[should_panic(expected = "`Acquire` polled after completion")]
+fn acquire_polled_after_completion() {
+    let sem = Semaphore::new(1);
+    let mut acquire = spawn(sem.acquire(1));
+    drop(assert_ready!(acquire.poll()));
+    let _ = acquire.poll();
+}
+
+#[test]
+fn wake_order_follows_policy() {
+    for (policy, expected) in [(Policy::Fifo, [0, 1, 2]), (Policy::Lifo, [2, 1, 0])] {
+        let sem = Semaphore::with_policy(1, policy);
+        let holder = sem.try_acquire(1).unwrap();
+        let mut waiters: Vec<_> = (0..3).map(|_| spawn(sem.acquire(1))).collect();
+        for waiter in &mut waiters {
+            assert_pending!(waiter.poll());
+        }
+
+        let mut permit = holder;
+        for i in expected {
+            drop(permit);
+            for (j, waiter) in waiters.iter().enumerate() {
+                assert_eq!(waiter.is_woken(), j == i, "{policy:?}");
+            }
+            permit = assert_ready!(waiters[i].poll());
+        }
+        drop(permit);
+        assert_eq!(sem.available_permits(), 1);
+    }
+
+    // multi-permit waiters, with permits released one by one
+    for (policy, first) in [(Policy::Fifo, 0), (Policy::Lifo, 1)] {
+        let sem = Semaphore::with_policy(2, policy);
+        let mut holder = sem.try_acquire(2).unwrap();
+        let mut waiters = vec![spawn(sem.acquire(2))];
+        assert_pending!(waiters[0].poll());
+        drop(holder.split(1).unwrap());
+        waiters.push(spawn(sem.acquire(2)));
+        assert_pending!(waiters[1].poll());
+        drop(holder);
+
+        let second = 1 - first;
+        assert!(waiters[first].is_woken(), "{policy:?}");
+        assert!(!waiters[second].is_woken(), "{policy:?}");
+        let permit = assert_ready!(waiters[first].poll());
+        assert_pending!(waiters[second].poll());
+        assert_eq!(sem.available_permits(), 0);
+
+        drop(permit);
+        assert!(waiters[second].is_woken(), "{policy:?}");
+        drop(assert_ready!(waiters[second].poll()));
+        assert_eq!(sem.available_permits(), 2);
+    }
+}
+
+#[test]
+fn forget_partial_retires_part_of_a_capped_permit() {
+    let sem = Semaphore::new_capped(4);
+    let mut permit = sem.try_acquire(3).unwrap();
+    assert_eq!(permit.forget_partial(2), 2);
+    drop(permit);
+    assert_eq!(sem.available_permits(), 2);
+
+    // the retired permits can be added back under the cap
+    sem.release(2);
+    assert_eq!(sem.available_permits(), 4);
+}
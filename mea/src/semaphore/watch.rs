@@ -0,0 +1,98 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::poll_fn;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::semaphore::Semaphore;
+
+impl Semaphore {
+    /// Returns a stream of the number of available permits, yielding each time it changes.
+    ///
+    /// The stream has watch semantics: it yields the current number first, and after that the
+    /// latest number whenever it differs from the last one yielded. Changes are coalesced, so
+    /// intermediate values may be skipped when the permits change faster than the stream is
+    /// polled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Semaphore::new(3);
+    /// let mut watch = sem.watch_available();
+    /// assert_eq!(watch.next().await, 3);
+    ///
+    /// let permit = sem.acquire(2).await;
+    /// assert_eq!(watch.next().await, 1);
+    /// drop(permit);
+    /// assert_eq!(watch.next().await, 3);
+    /// # }
+    /// ```
+    pub fn watch_available(&self) -> AvailabilityStream<'_> {
+        self.s.watch();
+        AvailabilityStream {
+            sem: self,
+            last: None,
+            key: None,
+        }
+    }
+}
+
+/// A stream of the number of available permits of a semaphore.
+///
+/// This type is created by the [`watch_available`] method. Dropping the stream deregisters it
+/// from the semaphore.
+///
+/// [`watch_available`]: Semaphore::watch_available
+#[derive(Debug)]
+pub struct AvailabilityStream<'a> {
+    sem: &'a Semaphore,
+    /// The number of available permits last yielded.
+    last: Option<u32>,
+    /// The key of the waker registered in the semaphore.
+    key: Option<usize>,
+}
+
+impl AvailabilityStream<'_> {
+    /// Waits for the number of available permits to change, and returns the latest number.
+    ///
+    /// The first call returns the current number immediately.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe: a change that happens while the call is cancelled is returned
+    /// by the next call.
+    pub async fn next(&mut self) -> u32 {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Polls for the number of available permits to change.
+    ///
+    /// The first call returns the current number immediately.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<u32> {
+        self.sem
+            .s
+            .poll_available_changed(&mut self.last, &mut self.key, cx)
+    }
+}
+
+impl Drop for AvailabilityStream<'_> {
+    fn drop(&mut self) {
+        self.sem.s.unwatch(self.key.take());
+    }
+}
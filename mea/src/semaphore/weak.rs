@@ -0,0 +1,83 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::sync::Weak;
+
+use crate::semaphore::Semaphore;
+
+impl Semaphore {
+    /// Creates a [`WeakSemaphore`] handle to this semaphore.
+    ///
+    /// The weak handle does not keep the semaphore alive. Owned permits do, since each of them
+    /// holds an [`Arc`] to the semaphore.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(1));
+    /// let weak = sem.downgrade();
+    /// assert!(weak.upgrade().is_some());
+    ///
+    /// drop(sem);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn downgrade(self: &Arc<Self>) -> WeakSemaphore {
+        WeakSemaphore {
+            sem: Arc::downgrade(self),
+        }
+    }
+}
+
+/// A weak handle to a [`Semaphore`] shared in an [`Arc`].
+///
+/// This type is created by the [`downgrade`] method. Like [`Weak`], it allows the semaphore to be
+/// dropped, and can be upgraded back to an `Arc<Semaphore>` as long as the semaphore is alive.
+///
+/// [`downgrade`]: Semaphore::downgrade
+#[derive(Debug, Clone)]
+pub struct WeakSemaphore {
+    sem: Weak<Semaphore>,
+}
+
+impl WeakSemaphore {
+    /// Attempts to upgrade the handle to an `Arc<Semaphore>`.
+    ///
+    /// Returns `None` if the semaphore has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use mea::semaphore::Semaphore;
+    ///
+    /// let sem = Arc::new(Semaphore::new(1));
+    /// let weak = sem.downgrade();
+    ///
+    /// // an outstanding owned permit keeps the semaphore alive
+    /// let permit = sem.try_acquire_owned(1).unwrap();
+    /// assert_eq!(weak.upgrade().unwrap().available_permits(), 0);
+    ///
+    /// drop(permit);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn upgrade(&self) -> Option<Arc<Semaphore>> {
+        self.sem.upgrade()
+    }
+}
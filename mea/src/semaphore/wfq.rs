@@ -0,0 +1,363 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+
+use slab::Slab;
+
+use crate::internal::Mutex;
+
+/// A semaphore that shares its permits across priority classes by weighted fair queuing.
+///
+/// Each waiter is queued in a class, and each class has a weight. When permits are contended,
+/// they are granted to the classes in proportion to their weights, using a deficit round-robin
+/// scheme: no class is starved, and within a class waiters are served in FIFO order.
+///
+/// When no task is waiting, permits are granted immediately regardless of the class.
+///
+/// # Examples
+///
+/// ```
+/// # #[tokio::main]
+/// # async fn main() {
+/// use mea::semaphore::WfqSemaphore;
+///
+/// // class 0 gets three permits for every permit granted to class 1 under contention
+/// let sem = WfqSemaphore::new(2, &[3, 1]);
+/// let high = sem.acquire_class(1, 0).await;
+/// let low = sem.acquire_class(1, 1).await;
+/// assert_eq!(sem.available_permits(), 0);
+/// # drop((high, low));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WfqSemaphore {
+    state: Mutex<WfqState>,
+}
+
+#[derive(Debug)]
+struct WfqState {
+    available: u32,
+    classes: Vec<Class>,
+    waiters: Slab<Waiter>,
+    /// The class currently being served in the round robin.
+    cursor: usize,
+    /// Whether the class at `cursor` has received its quantum for the current visit.
+    topped_up: bool,
+}
+
+#[derive(Debug)]
+struct Class {
+    weight: u64,
+    deficit: u64,
+    queue: VecDeque<usize>,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    permits: u32,
+    class: usize,
+    waker: Option<Waker>,
+    granted: bool,
+}
+
+impl WfqState {
+    fn has_queued(&self) -> bool {
+        self.classes.iter().any(|c| !c.queue.is_empty())
+    }
+
+    /// Skips the rounds in which no class could grant its first waiter, by adding the quanta of
+    /// those rounds at once.
+    ///
+    /// Only called at the start of a visit, so that every class with waiters is topped up exactly
+    /// once per round from here.
+    fn skip_idle_rounds(&mut self) {
+        let waiters = &self.waiters;
+        let rounds = self
+            .classes
+            .iter()
+            .filter_map(|class| {
+                let &key = class.queue.front()?;
+                let permits = u64::from(waiters[key].permits);
+                Some(permits.saturating_sub(class.deficit).div_ceil(class.weight))
+            })
+            .min();
+
+        // the last of the needed rounds is played out by the round robin
+        if let Some(skipped) = rounds.and_then(|rounds| rounds.checked_sub(1)) {
+            for class in self.classes.iter_mut() {
+                if !class.queue.is_empty() {
+                    class.deficit += skipped * class.weight;
+                }
+            }
+        }
+    }
+
+    /// Grants the available permits to queued waiters by deficit round robin.
+    fn dispatch(&mut self) {
+        while self.has_queued() {
+            if !self.topped_up {
+                self.skip_idle_rounds();
+            }
+            let class = &mut self.classes[self.cursor];
+            if !class.queue.is_empty() {
+                if !self.topped_up {
+                    class.deficit += class.weight;
+                    self.topped_up = true;
+                }
+
+                while let Some(&key) = class.queue.front() {
+                    let waiter = &mut self.waiters[key];
+                    let permits = u64::from(waiter.permits);
+                    if permits > class.deficit {
+                        break;
+                    }
+                    if waiter.permits > self.available {
+                        // keep serving this class once more permits are released
+                        return;
+                    }
+
+                    self.available -= waiter.permits;
+                    class.deficit -= permits;
+                    class.queue.pop_front();
+                    waiter.granted = true;
+                    if let Some(waker) = waiter.waker.take() {
+                        waker.wake();
+                    }
+                }
+
+                if class.queue.is_empty() {
+                    class.deficit = 0;
+                }
+            }
+
+            self.cursor = (self.cursor + 1) % self.classes.len();
+            self.topped_up = false;
+        }
+    }
+}
+
+impl WfqSemaphore {
+    /// Creates a new semaphore with the given number of permits, and one class per weight.
+    ///
+    /// A weight of zero is treated as one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weights` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::semaphore::WfqSemaphore;
+    ///
+    /// let sem = WfqSemaphore::new(10, &[4, 2, 1]);
+    /// assert_eq!(sem.available_permits(), 10);
+    /// ```
+    pub fn new(permits: u32, weights: &[u32]) -> Self {
+        assert!(!weights.is_empty(), "WfqSemaphore needs at least one class");
+        let classes = weights
+            .iter()
+            .map(|&w| Class {
+                weight: u64::from(w.max(1)),
+                deficit: 0,
+                queue: VecDeque::new(),
+            })
+            .collect();
+
+        Self {
+            state: Mutex::new(WfqState {
+                available: permits,
+                classes,
+                waiters: Slab::new(),
+                cursor: 0,
+                topped_up: false,
+            }),
+        }
+    }
+
+    /// Returns the current number of available permits.
+    pub fn available_permits(&self) -> u32 {
+        self.state.lock().available
+    }
+
+    /// Acquires `permits` permits as a member of the class `class`.
+    ///
+    /// If the permits are not immediately available, or other tasks are already waiting, the
+    /// task is queued in its class until the weighted fair queuing grants it the permits.
+    ///
+    /// # Cancel safety
+    ///
+    /// Cancelling a call to `acquire_class` makes you lose your place in the queue. Permits
+    /// granted to a cancelled call are given back to the semaphore.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `class` is not less than the number of weights the semaphore was created with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mea::semaphore::WfqSemaphore;
+    ///
+    /// let sem = WfqSemaphore::new(3, &[2, 1]);
+    /// let permit = sem.acquire_class(2, 1).await;
+    /// assert_eq!(permit.permits(), 2);
+    /// assert_eq!(sem.available_permits(), 1);
+    /// # }
+    /// ```
+    pub async fn acquire_class(&self, permits: u32, class: usize) -> WfqSemaphorePermit<'_> {
+        let classes = self.state.lock().classes.len();
+        assert!(class < classes, "class {class} out of {classes} classes");
+
+        let fut = AcquireClass {
+            sem: self,
+            permits,
+            class,
+            key: None,
+        };
+        fut.await;
+        WfqSemaphorePermit { sem: self, permits }
+    }
+
+    fn release(&self, permits: u32) {
+        let mut state = self.state.lock();
+        state.available = state
+            .available
+            .checked_add(permits)
+            .expect("number of permits overflow");
+        state.dispatch();
+    }
+}
+
+/// A permit from a [`WfqSemaphore`].
+///
+/// This type is created by the [`WfqSemaphore::acquire_class`] method. When the permit is
+/// dropped, the permits are returned to the semaphore.
+#[must_use = "permits are released immediately when dropped"]
+#[derive(Debug)]
+pub struct WfqSemaphorePermit<'a> {
+    sem: &'a WfqSemaphore,
+    permits: u32,
+}
+
+impl WfqSemaphorePermit<'_> {
+    /// Returns the number of permits this permit holds.
+    pub fn permits(&self) -> u32 {
+        self.permits
+    }
+}
+
+impl Drop for WfqSemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.sem.release(self.permits);
+    }
+}
+
+struct AcquireClass<'a> {
+    sem: &'a WfqSemaphore,
+    permits: u32,
+    class: usize,
+    key: Option<usize>,
+}
+
+impl fmt::Debug for AcquireClass<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcquireClass")
+            .field("permits", &self.permits)
+            .field("class", &self.class)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Future for AcquireClass<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Self {
+            sem,
+            permits,
+            class,
+            key,
+        } = self.get_mut();
+
+        let mut state = sem.state.lock();
+        match *key {
+            None => {
+                if state.waiters.is_empty() && state.available >= *permits {
+                    state.available -= *permits;
+                    return Poll::Ready(());
+                }
+
+                let k = state.waiters.insert(Waiter {
+                    permits: *permits,
+                    class: *class,
+                    waker: Some(cx.waker().clone()),
+                    granted: false,
+                });
+                state.classes[*class].queue.push_back(k);
+                state.dispatch();
+                if !state.waiters[k].granted {
+                    *key = Some(k);
+                    return Poll::Pending;
+                }
+                state.waiters.remove(k);
+                Poll::Ready(())
+            }
+            Some(k) => {
+                let waiter = &mut state.waiters[k];
+                if waiter.granted {
+                    state.waiters.remove(k);
+                    *key = None;
+                    return Poll::Ready(());
+                }
+
+                match waiter.waker {
+                    Some(ref w) if w.will_wake(cx.waker()) => {}
+                    _ => waiter.waker = Some(cx.waker().clone()),
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for AcquireClass<'_> {
+    fn drop(&mut self) {
+        let Some(k) = self.key.take() else {
+            return;
+        };
+
+        let mut state = self.sem.state.lock();
+        let waiter = state.waiters.remove(k);
+        if waiter.granted {
+            state.available += waiter.permits;
+        } else {
+            let queue = &mut state.classes[waiter.class].queue;
+            if let Some(pos) = queue.iter().position(|&x| x == k) {
+                queue.remove(pos);
+            }
+        }
+        // a cancelled head may have been blocking the others
+        state.dispatch();
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A one-shot signal that permanently releases all waiters once notified.
+//!
+//! A signal starts in the unnotified state. The first call to [`notify()`] arms the signal, waking
+//! up all pending waiters. After that, every call to [`wait()`] completes immediately; a signal
+//! never goes back to the unnotified state.
+//!
+//! This is useful for shutdown or readiness flags, where any number of tasks may start waiting
+//! before or after the event happens.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() {
+//! use std::sync::Arc;
+//!
+//! use mea::signal::Signal;
+//!
+//! let signal = Arc::new(Signal::new());
+//! let mut handles = Vec::new();
+//!
+//! for i in 0..3 {
+//!     let signal = signal.clone();
+//!     handles.push(tokio::spawn(async move {
+//!         signal.wait().await;
+//!         println!("Task {} is released", i);
+//!     }));
+//! }
+//!
+//! signal.notify();
+//! for handle in handles {
+//!     handle.await.unwrap();
+//! }
+//!
+//! // waits after notification complete immediately
+//! signal.wait().await;
+//! # }
+//! ```
+//!
+//! [`notify()`]: Signal::notify
+//! [`wait()`]: Signal::wait
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use crate::internal::CountdownState;
+use crate::semaphore::Cancellation;
+
+#[cfg(test)]
+mod tests;
+
+/// A one-shot signal that permanently releases all waiters once notified.
+///
+/// See the [module level documentation](self) for more.
+#[derive(Debug)]
+pub struct Signal {
+    state: CountdownState,
+}
+
+impl Default for Signal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Signal {
+    /// Creates a new signal in the unnotified state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::signal::Signal;
+    ///
+    /// let signal = Signal::new();
+    /// assert!(!signal.is_notified());
+    /// ```
+    pub const fn new() -> Self {
+        Self {
+            state: CountdownState::new(1),
+        }
+    }
+
+    /// Returns `true` if the signal has been notified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::signal::Signal;
+    ///
+    /// let signal = Signal::new();
+    /// signal.notify();
+    /// assert!(signal.is_notified());
+    /// ```
+    pub fn is_notified(&self) -> bool {
+        self.state.state() == 0
+    }
+
+    /// Notifies the signal, waking up all pending waiters.
+    ///
+    /// Only the first call has an effect; subsequent calls are no-op.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mea::signal::Signal;
+    ///
+    /// let signal = Signal::new();
+    /// signal.notify();
+    /// signal.notify(); // no-op
+    /// assert!(signal.is_notified());
+    /// ```
+    pub fn notify(&self) {
+        if self.state.decrement(1) {
+            self.state.wake_all();
+        }
+    }
+
+    /// Returns a future that will complete when the signal is notified.
+    ///
+    /// If the signal has already been notified, the future completes immediately without
+    /// registering a waker.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::sync::Arc;
+    ///
+    /// use mea::signal::Signal;
+    ///
+    /// let signal = Arc::new(Signal::new());
+    /// let signal2 = signal.clone();
+    ///
+    /// let handle = tokio::spawn(async move {
+    ///     signal2.wait().await;
+    ///     println!("Signal notified!");
+    /// });
+    ///
+    /// signal.notify();
+    /// handle.await.unwrap();
+    /// # }
+    /// ```
+    pub fn wait(&self) -> SignalWait<'_> {
+        SignalWait {
+            idx: None,
+            signal: self,
+        }
+    }
+}
+
+impl Cancellation for Signal {
+    type Cancelled<'a> = SignalWait<'a>;
+
+    fn cancelled(&self) -> Self::Cancelled<'_> {
+        self.wait()
+    }
+}
+
+/// A future returned by [`Signal::wait()`].
+///
+/// This future will complete when the signal is notified.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SignalWait<'a> {
+    idx: Option<usize>,
+    signal: &'a Signal,
+}
+
+impl fmt::Debug for SignalWait<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignalWait").finish_non_exhaustive()
+    }
+}
+
+impl Future for SignalWait<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Self { idx, signal } = self.get_mut();
+
+        if signal.state.spin_wait(0).is_err() {
+            signal.state.register_waker(idx, cx);
+            // double check after register waker, to catch the update between two steps
+            if signal.state.spin_wait(0).is_err() {
+                return Poll::Pending;
+            }
+        }
+
+        Poll::Ready(())
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright 2024 tison <wander4096@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use tokio_test::assert_pending;
+use tokio_test::assert_ready;
+use tokio_test::task::spawn;
+
+use crate::signal::Signal;
+
+#[test]
+fn notify_is_idempotent() {
+    let signal = Signal::new();
+    assert!(!signal.is_notified());
+    signal.notify();
+    assert!(signal.is_notified());
+    signal.notify();
+    assert!(signal.is_notified());
+}
+
+#[test]
+fn wait_after_notify_never_parks() {
+    let signal = Signal::new();
+    signal.notify();
+    for _ in 0..3 {
+        let mut f = spawn(signal.wait());
+        assert_ready!(f.poll());
+        assert!(!f.is_woken());
+    }
+}
+
+#[test]
+fn notify_wakes_pending_waiters() {
+    let signal = Signal::new();
+    let mut f1 = spawn(signal.wait());
+    let mut f2 = spawn(signal.wait());
+    assert_pending!(f1.poll());
+    assert_pending!(f2.poll());
+
+    signal.notify();
+    assert!(f1.is_woken());
+    assert!(f2.is_woken());
+    assert_ready!(f1.poll());
+    assert_ready!(f2.poll());
+}
+
+#[tokio::test]
+async fn concurrent_waiters() {
+    let signal = Arc::new(Signal::new());
+    let mut handles = Vec::new();
+    for _ in 0..16 {
+        let signal = signal.clone();
+        handles.push(tokio::spawn(async move { signal.wait().await }));
+    }
+
+    signal.notify();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+}